@@ -0,0 +1,199 @@
+//! Bosons on a bipartite lattice, sampled by the SSE of the equivalent
+//! [`Model`].
+//!
+//! A site with up to `n_max` bosons has the `n_max + 1` levels of a spin
+//! `n_max / 2`, so the operator string of [`State`] carries the occupations
+//! unchanged and only the vertex weights differ. Hard-core bosons are the
+//! spin 1/2 XXZ model in a field. On a bipartite lattice the sign of the
+//! hopping is a gauge choice, so the weights are the same for either sign.
+
+use crate::invalid_input;
+use crate::lattice::Lattice;
+use crate::model::Model;
+use crate::rng::{chain_rng, ChainRng};
+use crate::simulation::{Accumulator, MonteCarlo};
+use crate::state::State;
+use crate::stats;
+use crate::vertex::Vertices;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::io;
+
+/// `H = -t sum_<ij> (b^+_i b_j + h.c.) + V sum_<ij> n_i n_j - mu sum_i n_i +
+/// U/2 sum_i n_i (n_i - 1)` with at most `max_occupation` bosons per site.
+///
+/// The couplings of the lattice are the hopping of every bond relative to
+/// `hopping`, and the repulsion of a bond scales with them.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BoseHubbard {
+    pub hopping: f64,
+    pub repulsion: f64,
+    pub chemical_potential: f64,
+    pub onsite: f64,
+    pub max_occupation: usize,
+}
+
+/// `(mean, error)` of the bosonic observables of a chain.
+#[derive(Clone, Copy, Debug)]
+pub struct BosonEstimates {
+    /// Total energy
+    pub energy: (f64, f64),
+    /// `<N> / sites`
+    pub density: (f64, f64),
+    /// `beta (<N^2> - <N>^2) / sites`
+    pub compressibility: (f64, f64),
+    /// `sum_a <D_a^2> / (d beta sites)` over the `d` axes the lattice wraps
+    /// around, with `D_a` the net displacement of the worldlines along axis
+    /// `a`, see [`State::winding`]. Zero without periodic axes.
+    pub stiffness: (f64, f64),
+}
+
+impl BoseHubbard {
+    /// Nearest-neighbour hopping `hopping > 0` and repulsion `repulsion` at
+    /// the chemical potential `chemical_potential`.
+    pub fn hard_core(
+        hopping: f64,
+        repulsion: f64,
+        chemical_potential: f64,
+    ) -> io::Result<BoseHubbard> {
+        if !(hopping > 0.0 && hopping.is_finite()) {
+            return Err(invalid_input(format!(
+                "hopping {} is not positive",
+                hopping
+            )));
+        }
+        if !repulsion.is_finite() || !chemical_potential.is_finite() {
+            return Err(invalid_input(
+                "the repulsion and chemical potential must be finite",
+            ));
+        }
+        Ok(BoseHubbard {
+            hopping,
+            repulsion,
+            chemical_potential,
+            onsite: 0.0,
+            max_occupation: 1,
+        })
+    }
+    /// At most `max_occupation` bosons per site with the on-site repulsion `onsite`.
+    pub fn soft_core(mut self, onsite: f64, max_occupation: usize) -> io::Result<BoseHubbard> {
+        if !(1..=6).contains(&max_occupation) {
+            return Err(invalid_input("max_occupation must be between 1 and 6"));
+        }
+        if !onsite.is_finite() {
+            return Err(invalid_input(format!(
+                "on-site repulsion {} is not finite",
+                onsite
+            )));
+        }
+        self.onsite = onsite;
+        self.max_occupation = max_occupation;
+        Ok(self)
+    }
+
+    /// The model sampled on [`BoseHubbard::lattice`], with `epsilon = 1/4` as
+    /// free bosons would have no diagonal vertex weights.
+    pub fn model(&self) -> Model {
+        Model::xxz(self.repulsion / self.hopping)
+            .bosons(self.max_occupation)
+            .epsilon(0.25)
+            .field(self.chemical_potential)
+            .onsite(self.onsite)
+    }
+
+    /// `lattice` with every coupling multiplied by `hopping`.
+    pub fn lattice(&self, lattice: &Lattice) -> io::Result<Lattice> {
+        if !lattice.plaquettes.is_empty() {
+            return Err(invalid_input("bosons do not support plaquettes"));
+        }
+        lattice.scaled(self.hopping).map_err(invalid_input)
+    }
+
+    /// Time series of the replica on stream `chain`: energy, particle number,
+    /// its square and the squared displacement along the `axes`, with the
+    /// generator the chain left behind.
+    fn chain(
+        &self,
+        lattice: &Lattice,
+        axes: &[usize],
+        beta: f64,
+        mc: &MonteCarlo,
+        chain: u64,
+    ) -> ([Accumulator; 4], ChainRng) {
+        let rng = &mut chain_rng(mc.seed, chain);
+        let mut s = State::with_model(lattice, self.model(), 10, rng);
+        s.thermalize(beta, rng);
+        let [mut energies, mut particles, mut squares, mut windings] =
+            std::array::from_fn(|_| Accumulator::new());
+        for _ in 0..mc.samples {
+            let sample = s.sample(mc.nloop, beta, rng);
+            energies.push(sample.energy);
+            particles.push(sample.magnetization);
+            squares.push(sample.magnetization * sample.magnetization);
+            let winding = s.winding();
+            windings.push(axes.iter().map(|&a| winding[a] * winding[a]).sum::<f64>());
+        }
+        ([energies, particles, squares, windings], rng.clone())
+    }
+
+    /// Thermalizes `monte_carlo.chains` independent chains on `lattice` at
+    /// `beta`, on `monte_carlo.threads` threads, and estimates the bosonic
+    /// observables from `monte_carlo.samples` measurements of each.
+    ///
+    /// Replica `i` uses stream `i * 2^32` like [`crate::simulation::Simulation::replicas`].
+    pub fn run(
+        &self,
+        lattice: &Lattice,
+        beta: f64,
+        monte_carlo: &MonteCarlo,
+    ) -> io::Result<BosonEstimates> {
+        let mc = monte_carlo;
+        let lattice = self.lattice(lattice)?;
+        Vertices::check(&lattice, &self.model())?;
+        let sites = (lattice.num_even + lattice.num_odd) as f64;
+        let axes: Vec<usize> = (0..3).filter(|&axis| lattice.wraps(axis)).collect();
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(mc.threads)
+            .build()
+            .map_err(io::Error::other)?;
+        let replicas = pool.install(|| {
+            (0..mc.chains.max(1) as u64)
+                .into_par_iter()
+                .map(|i| self.chain(&lattice, &axes, beta, mc, i << 32))
+                .collect::<Vec<_>>()
+        });
+        let mut replicas = replicas.into_iter();
+        let ([mut energies, mut particles, mut squares, mut windings], mut rng) =
+            replicas.next().unwrap();
+        for ([e, p, s, w], _) in replicas {
+            energies.merge(&e);
+            particles.merge(&p);
+            squares.merge(&s);
+            windings.merge(&w);
+        }
+        let rng = &mut rng;
+        let mut estimate = |series: &Accumulator, scale: f64| {
+            let (mean, error) = series.estimate(mc.bin_size, mc.bootstraps, rng);
+            (mean * scale, error * scale)
+        };
+        let energy = estimate(&energies, 1.0);
+        let density = estimate(&particles, 1.0 / sites);
+        let stiffness = match axes.len() {
+            0 => (0.0, 0.0),
+            dimension => estimate(&windings, 1.0 / (dimension as f64 * beta * sites)),
+        };
+        let (fluctuation, error) = stats::bootstrap_fluctuation(
+            &particles.binned(mc.bin_size),
+            &squares.binned(mc.bin_size),
+            mc.bootstraps,
+            rng,
+        );
+        Ok(BosonEstimates {
+            energy,
+            density,
+            compressibility: (beta * fluctuation / sites, beta * error / sites),
+            stiffness,
+        })
+    }
+}
@@ -0,0 +1,412 @@
+use crate::invalid;
+use crate::lattice::{
+    Bipartite_Id_Vec, Boundary, CellSite, Edge, EdgeType, Even_Site_Id, Lattice, Odd_Site_Id,
+    Plaquette,
+};
+use crate::model::Model;
+use crate::rng::ChainRng;
+use crate::state::{Operator, OperatorId, OperatorType, Sample, State};
+use crate::vertex::Vertices;
+use id_collections::IdVec;
+use rand::SeedableRng;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Version of the on-disk format, bumped whenever the layout changes.
+pub const CHECKPOINT_VERSION: u32 = 8;
+
+/// A Markov chain together with everything needed to continue it bit-for-bit.
+#[derive(Clone, Debug)]
+pub struct Checkpoint {
+    pub state: State,
+    pub rng: ChainRng,
+    pub beta: f64,
+    pub nloop: usize,
+    /// Every sample taken so far.
+    pub samples: Vec<Sample>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct EdgeRecord {
+    id: usize,
+    even: usize,
+    odd: usize,
+    coupling: f64,
+    displacement: [i32; 3],
+}
+
+#[derive(Serialize, Deserialize)]
+struct PlaquetteRecord {
+    coupling: f64,
+    /// Ids of the two edges
+    edges: [usize; 2],
+}
+
+/// A value for every even and every odd site.
+#[derive(Serialize, Deserialize)]
+struct SitesRecord<T> {
+    even: Vec<T>,
+    odd: Vec<T>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct OperatorRecord {
+    edge: EdgeRecord,
+    plaquette: Option<usize>,
+    /// Level of every leg
+    legs: [u8; 8],
+    links: [usize; 8],
+}
+
+#[derive(Serialize, Deserialize)]
+struct RngRecord {
+    seed: [u8; 32],
+    stream: u64,
+    word_pos: u128,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CheckpointRecord {
+    version: u32,
+    beta: f64,
+    nloop: usize,
+    model: Model,
+    num_even: usize,
+    num_odd: usize,
+    edges: Vec<EdgeRecord>,
+    plaquettes: Vec<PlaquetteRecord>,
+    boundaries: [Boundary; 3],
+    edge_types: Vec<Option<String>>,
+    positions: Option<SitesRecord<[f64; 3]>>,
+    cells: Option<SitesRecord<CellSite>>,
+    alpha_even: Vec<u8>,
+    alpha_odd: Vec<u8>,
+    path: Vec<Option<OperatorRecord>>,
+    n: usize,
+    rng: RngRecord,
+    samples: Vec<Sample>,
+}
+
+#[derive(Deserialize)]
+struct VersionRecord {
+    version: u32,
+}
+
+impl EdgeRecord {
+    fn new(edge: &Edge) -> EdgeRecord {
+        EdgeRecord {
+            id: edge.id,
+            even: edge.even.0,
+            odd: edge.odd.0,
+            coupling: edge.coupling,
+            displacement: edge.displacement,
+        }
+    }
+    fn edge(&self) -> Edge {
+        Edge {
+            id: self.id,
+            coupling: self.coupling,
+            displacement: self.displacement,
+            even: Even_Site_Id(self.even),
+            odd: Odd_Site_Id(self.odd),
+        }
+    }
+}
+
+impl<T: Clone> SitesRecord<T> {
+    fn new(sites: &Bipartite_Id_Vec<T>) -> SitesRecord<T> {
+        SitesRecord {
+            even: sites.even.iter().map(|(_, v)| v.clone()).collect(),
+            odd: sites.odd.iter().map(|(_, v)| v.clone()).collect(),
+        }
+    }
+    /// The values, if there is one for each of the `num_even` and `num_odd`
+    /// sites.
+    fn sites(self, num_even: usize, num_odd: usize, what: &str) -> io::Result<Bipartite_Id_Vec<T>> {
+        if self.even.len() != num_even || self.odd.len() != num_odd {
+            return Err(invalid(format!("{} do not match the lattice", what)));
+        }
+        let mut sites = Bipartite_Id_Vec::new();
+        for value in self.even {
+            let _ = sites.even.push(value);
+        }
+        for value in self.odd {
+            let _ = sites.odd.push(value);
+        }
+        Ok(sites)
+    }
+}
+
+/// Checks what [`Vertices::new`] would otherwise assert, so that a damaged
+/// file is an error and not a panic.
+fn check_record(record: &CheckpointRecord) -> io::Result<()> {
+    let model = &record.model;
+    if let Some(reason) = model.unsupported() {
+        return Err(invalid(reason));
+    }
+    if !record.beta.is_finite() || record.beta <= 0.0 {
+        return Err(invalid(format!("beta {} is not positive", record.beta)));
+    }
+    if !record.plaquettes.is_empty() && model.levels() != 2 {
+        return Err(invalid("plaquette operators need spin 1/2"));
+    }
+    Ok(())
+}
+
+impl Checkpoint {
+    pub fn new(state: State, rng: ChainRng, beta: f64, nloop: usize) -> Checkpoint {
+        Checkpoint {
+            state,
+            rng,
+            beta,
+            nloop,
+            samples: Vec::new(),
+        }
+    }
+
+    /// Writes the checkpoint, replacing `path` atomically so a crash while
+    /// saving never destroys the previous checkpoint.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let state = &self.state;
+        let record = CheckpointRecord {
+            version: CHECKPOINT_VERSION,
+            beta: self.beta,
+            nloop: self.nloop,
+            model: state.model,
+            num_even: state.latice.num_even,
+            num_odd: state.latice.num_odd,
+            edges: state.latice.edges.iter().map(EdgeRecord::new).collect(),
+            plaquettes: state
+                .latice
+                .plaquettes
+                .iter()
+                .map(|p| PlaquetteRecord {
+                    coupling: p.coupling,
+                    edges: p.edges.map(|e| e.id),
+                })
+                .collect(),
+            boundaries: state.latice.boundaries,
+            edge_types: state
+                .latice
+                .edge_types
+                .iter()
+                .map(|t| t.as_ref().map(|t| t.0.clone()))
+                .collect(),
+            positions: state.latice.positions.as_ref().map(SitesRecord::new),
+            cells: state.latice.cells.as_ref().map(SitesRecord::new),
+            alpha_even: state.alpha.even.iter().map(|(_, &s)| s).collect(),
+            alpha_odd: state.alpha.odd.iter().map(|(_, &s)| s).collect(),
+            path: state
+                .path
+                .iter()
+                .map(|(_, op)| {
+                    op.as_ref().map(|op| OperatorRecord {
+                        edge: EdgeRecord::new(&op.edge),
+                        plaquette: op.plaquette.map(|p| p.id),
+                        legs: op.legs,
+                        links: op.links.map(|link| link.0),
+                    })
+                })
+                .collect(),
+            n: state.n,
+            rng: RngRecord {
+                seed: self.rng.get_seed(),
+                stream: self.rng.get_stream(),
+                word_pos: self.rng.get_word_pos(),
+            },
+            samples: self.samples.clone(),
+        };
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        fs::write(&tmp, serde_json::to_vec(&record)?)?;
+        fs::rename(tmp, path)
+    }
+
+    /// Reads a checkpoint written by [`Checkpoint::save`] and verifies the
+    /// restored operator string.
+    pub fn load(path: &Path) -> io::Result<Checkpoint> {
+        let data = fs::read(path)?;
+        let version: VersionRecord = serde_json::from_slice(&data)?;
+        if version.version != CHECKPOINT_VERSION {
+            return Err(invalid(format!(
+                "checkpoint version {} is not supported, expected {}",
+                version.version, CHECKPOINT_VERSION
+            )));
+        }
+        let record: CheckpointRecord = serde_json::from_slice(&data)?;
+        check_record(&record)?;
+        if record.alpha_even.len() != record.num_even || record.alpha_odd.len() != record.num_odd {
+            return Err(invalid("alpha does not match the lattice"));
+        }
+        let levels = record.model.levels() as u8;
+        let legs = record.path.iter().flatten().flat_map(|op| op.legs);
+        let alpha = record.alpha_even.iter().chain(record.alpha_odd.iter());
+        if legs.chain(alpha.copied()).any(|level| level >= levels) {
+            return Err(invalid(format!("levels must be below {}", levels)));
+        }
+        let edges: Vec<Edge> = record.edges.iter().map(EdgeRecord::edge).collect();
+        let mut plaquettes = Vec::new();
+        for plaquette in record.plaquettes.iter() {
+            let edge = |id: usize| {
+                edges
+                    .get(id)
+                    .copied()
+                    .ok_or_else(|| invalid(format!("plaquette edge {} does not exist", id)))
+            };
+            let [a, b] = [edge(plaquette.edges[0])?, edge(plaquette.edges[1])?];
+            plaquettes.push(Plaquette {
+                id: plaquettes.len(),
+                coupling: plaquette.coupling,
+                edges: [a, b],
+            });
+        }
+        if record.edge_types.len() != edges.len() {
+            return Err(invalid("edge types do not match the lattice"));
+        }
+        let mut latice = Lattice::new(record.num_even, record.num_odd, edges)
+            .and_then(|latice| latice.with_plaquettes(plaquettes))
+            .map_err(invalid)?;
+        latice.boundaries = record.boundaries;
+        latice.edge_types = record
+            .edge_types
+            .into_iter()
+            .map(|t| t.map(EdgeType))
+            .collect();
+        if let Some(positions) = record.positions {
+            latice.positions =
+                Some(positions.sites(record.num_even, record.num_odd, "positions")?);
+        }
+        if let Some(cells) = record.cells {
+            latice.cells = Some(cells.sites(record.num_even, record.num_odd, "cells")?);
+        }
+        if let Some(problem) = record.model.sign_problem(&latice) {
+            return Err(invalid(problem));
+        }
+        let mut alpha = Bipartite_Id_Vec::new();
+        for s in record.alpha_even {
+            let _ = alpha.even.push(s);
+        }
+        for s in record.alpha_odd {
+            let _ = alpha.odd.push(s);
+        }
+        let mut path = IdVec::new();
+        let length = record.path.len();
+        for op in record.path {
+            let op = match op {
+                Some(op) => {
+                    let plaquette =
+                        match op.plaquette {
+                            Some(id) => Some(*latice.plaquettes.get(id).ok_or_else(|| {
+                                invalid(format!("plaquette {} does not exist", id))
+                            })?),
+                            None => None,
+                        };
+                    // the operator must sit on a bond of the lattice
+                    let edge = match plaquette {
+                        Some(plaquette) => plaquette.edges[0],
+                        None => *latice.edges.get(op.edge.id).ok_or_else(|| {
+                            invalid(format!("operator edge {} does not exist", op.edge.id))
+                        })?,
+                    };
+                    if (edge.even.0, edge.odd.0) != (op.edge.even, op.edge.odd) {
+                        return Err(invalid(format!(
+                            "operator edge {} does not match the lattice",
+                            op.edge.id
+                        )));
+                    }
+                    let legs = if plaquette.is_some() { 8 } else { 4 };
+                    if op.links[..legs].iter().any(|&link| link >= length) {
+                        return Err(invalid("operator links leave the path"));
+                    }
+                    let mut op = Operator {
+                        operator_type: OperatorType::D,
+                        edge,
+                        plaquette,
+                        legs: op.legs,
+                        links: op.links.map(OperatorId),
+                    };
+                    op.update_type();
+                    Some(op)
+                }
+                None => None,
+            };
+            let _ = path.push(op);
+        }
+        let state = State {
+            alpha,
+            path,
+            n: record.n,
+            vertices: Vertices::new(&latice, &record.model),
+            model: record.model,
+            latice,
+        };
+        state.check().map_err(invalid)?;
+        let mut rng = ChainRng::from_seed(record.rng.seed);
+        rng.set_stream(record.rng.stream);
+        rng.set_word_pos(record.rng.word_pos);
+        Ok(Checkpoint {
+            state,
+            rng,
+            beta: record.beta,
+            nloop: record.nloop,
+            samples: record.samples,
+        })
+    }
+
+    /// Checks that the checkpoint continues a chain at `beta` of `model` on
+    /// `lattice`, so that resuming never mixes the samples of two runs.
+    pub fn check_parameters(&self, lattice: &Lattice, model: &Model, beta: f64) -> io::Result<()> {
+        let mismatch = |what: &str| {
+            Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("the checkpoint was taken with another {}", what),
+            ))
+        };
+        if self.beta != beta {
+            return mismatch(&format!("beta, {} instead of {}", self.beta, beta));
+        }
+        if self.state.model != *model {
+            return mismatch("model");
+        }
+        let ours = &self.state.latice;
+        let same_edge = |a: &Edge, b: &Edge| {
+            (a.id, a.even, a.odd, a.displacement) == (b.id, b.even, b.odd, b.displacement)
+                && a.coupling == b.coupling
+        };
+        let same_lattice = (ours.num_even, ours.num_odd) == (lattice.num_even, lattice.num_odd)
+            && ours.boundaries == lattice.boundaries
+            && ours.edges.len() == lattice.edges.len()
+            && ours
+                .edges
+                .iter()
+                .zip(lattice.edges.iter())
+                .all(|(a, b)| same_edge(a, b))
+            && ours.plaquettes.len() == lattice.plaquettes.len()
+            && ours
+                .plaquettes
+                .iter()
+                .zip(lattice.plaquettes.iter())
+                .all(|(a, b)| {
+                    a.coupling == b.coupling && a.edges.map(|e| e.id) == b.edges.map(|e| e.id)
+                });
+        if !same_lattice {
+            return mismatch("lattice or couplings");
+        }
+        Ok(())
+    }
+
+    /// Samples until `total` samples have been taken, saving to `path` every
+    /// `every` samples and once more at the end.
+    pub fn run(&mut self, total: usize, every: usize, path: &Path) -> io::Result<()> {
+        while self.samples.len() < total {
+            let sample = self.state.sample(self.nloop, self.beta, &mut self.rng);
+            self.samples.push(sample);
+            if self.samples.len().is_multiple_of(every) {
+                self.save(path)?;
+            }
+        }
+        self.save(path)
+    }
+}
@@ -0,0 +1,632 @@
+use clap::{Args, Parser, Subcommand};
+use montecarlo::boson::BoseHubbard;
+use montecarlo::config::RunConfig;
+use montecarlo::ed::{ground_state_with_model, ExactDiagonalization};
+use montecarlo::ensemble::Ensemble;
+use montecarlo::lattice::{make_jq_latice, make_latice, Lattice};
+use montecarlo::model::Model;
+use montecarlo::rng::chain_rng;
+use montecarlo::simulation::{MonteCarlo, Observable, Simulation};
+use montecarlo::state::State;
+use montecarlo::stats::{self, range, write_csv};
+use montecarlo::tempering::Tempering;
+use montecarlo::tfim::TransverseIsing;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+#[derive(Parser, Debug)]
+#[command(about = "Stochastic series expansion QMC for the dimerized Heisenberg model")]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Sample a single chain and report energy and staggered magnetization
+    Run(RunArgs),
+    /// Sample the transverse-field Ising model on the same lattices
+    Tfim(TfimArgs),
+    /// Density, compressibility and superfluid stiffness of hard- or soft-core bosons
+    Bosons(BosonArgs),
+    /// Sweep j1 for several lattice sizes and betas, running the points in parallel
+    Sweep(SweepArgs),
+    /// Replica exchange along a ladder of betas or of j1 values
+    Temper(TemperArgs),
+    /// Record the expansion order and operator string length during equilibration
+    ThermalizeTrace(TraceArgs),
+    /// Magnetization per site as a function of the uniform field
+    MagnetizationCurve(CurveArgs),
+    /// Exact diagonalization reference values
+    Ed(EdArgs),
+    /// Binned bootstrap of every column of a csv of samples
+    Analyze(AnalyzeArgs),
+    /// Execute a campaign described by a TOML or JSON run configuration
+    Config(ConfigArgs),
+}
+
+/// The `width x height` torus with columnar dimers of strength `j1` and
+/// optionally the plaquette term `q` of the J-Q model.
+#[derive(Args, Debug, Clone)]
+pub struct LatticeArgs {
+    #[arg(long, default_value_t = 4)]
+    pub width: usize,
+    #[arg(long, default_value_t = 4)]
+    pub height: usize,
+    /// Coupling of every other vertical bond, all other bonds are 1
+    #[arg(long, default_value_t = 1.0)]
+    pub j1: f64,
+    /// Coupling of the products of singlet projectors on every square
+    #[arg(long, default_value_t = 0.0)]
+    pub q: f64,
+}
+
+impl LatticeArgs {
+    pub fn build(&self) -> io::Result<Lattice> {
+        self.with_j1(self.j1)
+    }
+    pub fn with_j1(&self, j1: f64) -> io::Result<Lattice> {
+        if self.q == 0.0 {
+            make_latice(self.width, self.height, j1).map_err(invalid_input)
+        } else {
+            make_jq_latice(self.width, self.height, j1, self.q).map_err(invalid_input)
+        }
+    }
+}
+
+/// Spin, anisotropy of the XXZ model on every bond and the longitudinal fields.
+#[derive(Args, Debug, Clone)]
+pub struct ModelArgs {
+    /// Spin of every site, a multiple of 1/2
+    #[arg(long, default_value_t = 0.5)]
+    pub spin: f64,
+    /// 1 is the Heisenberg model, larger is easy-axis and smaller easy-plane
+    #[arg(long, default_value_t = 1.0)]
+    pub delta: f64,
+    /// Extra diagonal constant per unit coupling, gives parallel spins a vertex weight
+    #[arg(long, default_value_t = 0.0)]
+    pub epsilon: f64,
+    /// Uniform field h coupling to sum_i S^z_i
+    #[arg(long, default_value_t = 0.0)]
+    pub field: f64,
+    /// Field added on even and subtracted on odd sites
+    #[arg(long, default_value_t = 0.0)]
+    pub staggered_field: f64,
+}
+
+impl ModelArgs {
+    pub fn build(&self) -> io::Result<Model> {
+        let model = Model {
+            spin: self.spin,
+            delta: self.delta,
+            epsilon: self.epsilon,
+            field: self.field,
+            staggered_field: self.staggered_field,
+            ..Model::default()
+        };
+        match model.unsupported() {
+            Some(reason) => Err(invalid_input(reason)),
+            None => Ok(model),
+        }
+    }
+}
+
+/// Monte Carlo budget and error analysis.
+#[derive(Args, Debug, Clone)]
+pub struct McArgs {
+    /// Number of measurements after thermalization
+    #[arg(long, default_value_t = 10000)]
+    pub samples: usize,
+    /// Loop updates per Monte Carlo step
+    #[arg(long, default_value_t = 40)]
+    pub nloop: usize,
+    #[arg(long, default_value_t = 100)]
+    pub bin_size: usize,
+    #[arg(long, default_value_t = 1000)]
+    pub bootstraps: usize,
+    /// Top level seed, every chain derives its own stream from it
+    #[arg(long, default_value_t = 42)]
+    pub seed: u64,
+    /// Independent chains per parameter point, merged into one estimate
+    #[arg(long, default_value_t = 1)]
+    pub chains: usize,
+    /// Worker threads, 0 uses every core
+    #[arg(long, default_value_t = 0)]
+    pub threads: usize,
+}
+
+impl McArgs {
+    pub fn monte_carlo(&self) -> io::Result<MonteCarlo> {
+        if self.bin_size == 0 {
+            return Err(invalid_input("bin_size must be positive"));
+        }
+        Ok(MonteCarlo {
+            samples: self.samples,
+            nloop: self.nloop,
+            bin_size: self.bin_size,
+            bootstraps: self.bootstraps,
+            seed: self.seed,
+            chains: self.chains,
+            threads: self.threads,
+        })
+    }
+}
+
+#[derive(Args, Debug)]
+pub struct RunArgs {
+    #[command(flatten)]
+    pub lattice: LatticeArgs,
+    #[command(flatten)]
+    pub model: ModelArgs,
+    #[arg(long)]
+    pub beta: f64,
+    #[command(flatten)]
+    pub mc: McArgs,
+    /// Write every `(energy, staggered magnetization, magnetization)` sample to this csv
+    #[arg(long)]
+    pub output: Option<PathBuf>,
+    /// Checkpoint file, resumed from if it already exists
+    #[arg(long)]
+    pub checkpoint: Option<PathBuf>,
+    #[arg(long, default_value_t = 1000)]
+    pub checkpoint_every: usize,
+}
+
+#[derive(Args, Debug)]
+pub struct TfimArgs {
+    #[command(flatten)]
+    pub lattice: LatticeArgs,
+    /// Transverse field h coupling to sum_i S^x_i
+    #[arg(long)]
+    pub field: f64,
+    #[arg(long)]
+    pub beta: f64,
+    #[command(flatten)]
+    pub mc: McArgs,
+    /// Write every `(energy, staggered magnetization, magnetization)` sample to this csv
+    #[arg(long)]
+    pub output: Option<PathBuf>,
+}
+
+/// Bose-Hubbard model on the lattice, whose couplings are relative hoppings.
+#[derive(Args, Debug)]
+pub struct BosonArgs {
+    #[command(flatten)]
+    pub lattice: LatticeArgs,
+    /// Hopping t
+    #[arg(long, default_value_t = 1.0)]
+    pub hopping: f64,
+    /// Nearest-neighbour repulsion V
+    #[arg(long, default_value_t = 0.0)]
+    pub repulsion: f64,
+    /// Chemical potential mu
+    #[arg(long, default_value_t = 0.0)]
+    pub chemical_potential: f64,
+    /// On-site repulsion U, only felt with more than one boson per site
+    #[arg(long, default_value_t = 0.0)]
+    pub onsite: f64,
+    /// Largest occupation of a site, 1 for hard-core bosons
+    #[arg(long, default_value_t = 1)]
+    pub max_occupation: usize,
+    #[arg(long)]
+    pub beta: f64,
+    #[command(flatten)]
+    pub mc: McArgs,
+}
+
+#[derive(Args, Debug)]
+pub struct SweepArgs {
+    /// Linear sizes L of the L x L lattices
+    #[arg(long, value_delimiter = ',', default_value = "2")]
+    pub sizes: Vec<usize>,
+    #[arg(
+        long,
+        value_delimiter = ',',
+        conflicts_with = "beta_per_length",
+        required_unless_present = "beta_per_length"
+    )]
+    pub betas: Vec<f64>,
+    /// Use beta = factor * L instead of a fixed list of betas
+    #[arg(long)]
+    pub beta_per_length: Option<f64>,
+    #[arg(long, default_value_t = 0.0)]
+    pub j1_min: f64,
+    #[arg(long, default_value_t = 2.0)]
+    pub j1_max: f64,
+    #[arg(long, default_value_t = 20)]
+    pub j1_steps: usize,
+    #[command(flatten)]
+    pub model: ModelArgs,
+    /// `energy` or `staggered-magnetization`
+    #[arg(long, default_value = "energy")]
+    pub observable: Observable,
+    #[command(flatten)]
+    pub mc: McArgs,
+    /// Csv with j1 in the first column followed by mean and error per size and beta
+    #[arg(long)]
+    pub output: PathBuf,
+    /// Checkpoint every chain into this directory and resume the ones present
+    #[arg(long)]
+    pub checkpoint_dir: Option<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+pub struct TemperArgs {
+    /// Lattice of a beta ladder; its j1 is ignored by a j1 ladder
+    #[command(flatten)]
+    pub lattice: LatticeArgs,
+    #[command(flatten)]
+    pub model: ModelArgs,
+    /// Ladder in beta
+    #[arg(
+        long,
+        value_delimiter = ',',
+        conflicts_with = "j1s",
+        required_unless_present = "j1s"
+    )]
+    pub betas: Vec<f64>,
+    /// Ladder in j1 at fixed `--beta`
+    #[arg(long, value_delimiter = ',', requires = "beta")]
+    pub j1s: Vec<f64>,
+    #[arg(long)]
+    pub beta: Option<f64>,
+    /// Sampling steps between swap proposals
+    #[arg(long, default_value_t = 1)]
+    pub swap_every: usize,
+    #[command(flatten)]
+    pub mc: McArgs,
+    /// Csv with beta, j1, energy, error, staggered magnetization, error and the
+    /// acceptance rate of swaps with the next rung, one row per rung
+    #[arg(long)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+pub struct CurveArgs {
+    #[command(flatten)]
+    pub lattice: LatticeArgs,
+    /// Model at every point, its `--field` is replaced by the swept one
+    #[command(flatten)]
+    pub model: ModelArgs,
+    #[arg(long)]
+    pub beta: f64,
+    #[arg(long, default_value_t = 0.0)]
+    pub h_min: f64,
+    #[arg(long, default_value_t = 4.0)]
+    pub h_max: f64,
+    #[arg(long, default_value_t = 20)]
+    pub h_steps: usize,
+    #[command(flatten)]
+    pub mc: McArgs,
+    /// Csv with h, magnetization per site and its error, one row per field
+    #[arg(long)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+pub struct TraceArgs {
+    #[command(flatten)]
+    pub lattice: LatticeArgs,
+    #[arg(long, default_value_t = 16.0)]
+    pub beta: f64,
+    #[arg(long, default_value_t = 100000)]
+    pub steps: usize,
+    #[arg(long, default_value_t = 40)]
+    pub nloop: usize,
+    #[arg(long, default_value_t = 42)]
+    pub seed: u64,
+    #[arg(long, default_value = "question8.csv")]
+    pub output: PathBuf,
+}
+
+#[derive(Args, Debug)]
+pub struct EdArgs {
+    #[command(flatten)]
+    pub lattice: LatticeArgs,
+    #[command(flatten)]
+    pub model: ModelArgs,
+    #[arg(long, value_delimiter = ',', default_value = "1,2,4,8,16")]
+    pub betas: Vec<f64>,
+    /// Only compute the ground state with Lanczos, for lattices too large for full ED
+    #[arg(long)]
+    pub ground_state: bool,
+    /// Write the table to this csv as well as printing it
+    #[arg(long)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+pub struct AnalyzeArgs {
+    /// Csv of samples, one column per observable
+    pub input: PathBuf,
+    #[arg(long, default_value_t = 100)]
+    pub bin_size: usize,
+    #[arg(long, default_value_t = 1000)]
+    pub bootstraps: usize,
+    #[arg(long, default_value_t = 42)]
+    pub seed: u64,
+}
+
+#[derive(Args, Debug)]
+pub struct ConfigArgs {
+    /// Run configuration, `.toml` or `.json`
+    pub path: PathBuf,
+}
+
+fn invalid_input(error: impl ToString) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, error.to_string())
+}
+
+pub fn execute(cli: Cli) -> io::Result<()> {
+    match cli.command {
+        Command::Run(args) => run(args),
+        Command::Tfim(args) => tfim(args),
+        Command::Bosons(args) => bosons(args),
+        Command::Sweep(args) => sweep(args),
+        Command::Temper(args) => temper(args),
+        Command::MagnetizationCurve(args) => magnetization_curve(args),
+        Command::ThermalizeTrace(args) => thermalize_trace(args),
+        Command::Ed(args) => ed(args),
+        Command::Analyze(args) => analyze(args),
+        Command::Config(args) => RunConfig::load(&args.path)?.execute(),
+    }
+}
+
+fn run(args: RunArgs) -> io::Result<()> {
+    let mut simulation = Simulation::new(args.lattice.build()?, args.beta)
+        .model(args.model.build()?)
+        .monte_carlo(args.mc.monte_carlo()?);
+    if let Some(path) = args.checkpoint {
+        simulation = simulation.checkpoint(path, args.checkpoint_every);
+    }
+    let mut ensemble = Ensemble::new();
+    ensemble.push(simulation);
+    let mut measurements = ensemble.run()?.remove(0);
+    let (energy, energy_sd) = measurements.estimate(Observable::Energy);
+    let (sm, sm_sd) = measurements.estimate(Observable::StaggeredMagnetization);
+    let (m, m_sd) = measurements.estimate(Observable::Magnetization);
+    println!("energy = {} +- {}", energy, energy_sd);
+    println!("staggered magnetization = {} +- {}", sm, sm_sd);
+    println!("magnetization = {} +- {}", m, m_sd);
+    if let Some(output) = args.output {
+        let columns = [
+            measurements.energy.samples().to_vec(),
+            measurements.staggered_magnetization.samples().to_vec(),
+            measurements.magnetization.samples().to_vec(),
+        ];
+        write_csv(output, &columns)?;
+    }
+    Ok(())
+}
+
+fn tfim(args: TfimArgs) -> io::Result<()> {
+    if !(args.field >= 0.0 && args.field.is_finite()) {
+        return Err(invalid_input("the transverse field must not be negative"));
+    }
+    let simulation = Simulation::new(args.lattice.build()?, args.beta)
+        .transverse_ising(TransverseIsing::new(args.field))
+        .monte_carlo(args.mc.monte_carlo()?);
+    let mut ensemble = Ensemble::new();
+    ensemble.push(simulation);
+    let mut measurements = ensemble.run()?.remove(0);
+    for observable in Observable::ALL {
+        let (mean, sd) = measurements.estimate(observable);
+        println!("{:?} = {} +- {}", observable, mean, sd);
+    }
+    if let Some(output) = args.output {
+        let columns = Observable::ALL.map(|o| measurements.observable(o).samples().to_vec());
+        write_csv(output, &columns)?;
+    }
+    Ok(())
+}
+
+fn bosons(args: BosonArgs) -> io::Result<()> {
+    let model = BoseHubbard::hard_core(args.hopping, args.repulsion, args.chemical_potential)?
+        .soft_core(args.onsite, args.max_occupation)?;
+    let estimates = model.run(&args.lattice.build()?, args.beta, &args.mc.monte_carlo()?)?;
+    for (name, (mean, sd)) in [
+        ("energy", estimates.energy),
+        ("density", estimates.density),
+        ("compressibility", estimates.compressibility),
+        ("stiffness", estimates.stiffness),
+    ] {
+        println!("{} = {} +- {}", name, mean, sd);
+    }
+    Ok(())
+}
+
+fn sweep(args: SweepArgs) -> io::Result<()> {
+    if args.j1_steps == 0 {
+        return Err(invalid_input("j1_steps must be positive"));
+    }
+    if args.beta_per_length.is_none() && args.betas.is_empty() {
+        return Err(invalid_input("the sweep needs at least one beta"));
+    }
+    let model = args.model.build()?;
+    let js = range(args.j1_min, args.j1_max, args.j1_steps);
+    if let Some(dir) = &args.checkpoint_dir {
+        fs::create_dir_all(dir)?;
+    }
+    let mut ensemble = Ensemble::new();
+    for L in args.sizes.iter() {
+        let betas = match args.beta_per_length {
+            Some(factor) => vec![factor * *L as f64],
+            None => args.betas.clone(),
+        };
+        for beta in betas {
+            for j in js.iter() {
+                let mut simulation =
+                    Simulation::new(make_latice(*L, *L, *j).map_err(invalid_input)?, beta)
+                        .model(model)
+                        .monte_carlo(args.mc.monte_carlo()?)
+                        .chain(ensemble.len() as u64);
+                if let Some(dir) = &args.checkpoint_dir {
+                    let path = dir.join(format!("sweep_L{}_beta{}_j{}.json", L, beta, j));
+                    simulation = simulation.checkpoint(path, 1000);
+                }
+                ensemble.push(simulation);
+            }
+        }
+    }
+    let mut columns = vec![js.clone()];
+    for chunk in ensemble.run()?.chunks_mut(js.len()) {
+        let (means, sds) = chunk
+            .iter_mut()
+            .map(|measurements| measurements.estimate(args.observable))
+            .unzip();
+        columns.push(means);
+        columns.push(sds);
+    }
+    write_csv(args.output, &columns)?;
+    Ok(())
+}
+
+fn temper(args: TemperArgs) -> io::Result<()> {
+    let tempering = match args.beta {
+        Some(beta) if !args.j1s.is_empty() => {
+            let lattices = args
+                .j1s
+                .iter()
+                .map(|j1| args.lattice.with_j1(*j1))
+                .collect::<io::Result<_>>()?;
+            Tempering::in_coupling(lattices, beta)
+        }
+        _ => Tempering::in_beta(args.lattice.build()?, &args.betas),
+    };
+    let j1s = match args.j1s.is_empty() {
+        true => vec![args.lattice.j1; args.betas.len()],
+        false => args.j1s.clone(),
+    };
+    let tempering = tempering
+        .model(args.model.build()?)
+        .monte_carlo(args.mc.monte_carlo()?)
+        .swap_every(args.swap_every);
+    let betas: Vec<f64> = tempering.rungs().iter().map(|rung| rung.beta).collect();
+    let mut result = tempering.run()?;
+
+    let mut columns = vec![Vec::new(); 7];
+    println!("beta j1 energy error staggered_magnetization error acceptance");
+    for (i, measurements) in result.rungs.iter_mut().enumerate() {
+        let (energy, energy_sd) = measurements.estimate(Observable::Energy);
+        let (sm, sm_sd) = measurements.estimate(Observable::StaggeredMagnetization);
+        let acceptance = result.acceptance.get(i).copied().unwrap_or(f64::NAN);
+        let row = [betas[i], j1s[i], energy, energy_sd, sm, sm_sd, acceptance];
+        println!("{:?}", row);
+        for (column, value) in columns.iter_mut().zip(row) {
+            column.push(value);
+        }
+    }
+    if let Some(output) = args.output {
+        write_csv(output, &columns)?;
+    }
+    Ok(())
+}
+
+fn magnetization_curve(args: CurveArgs) -> io::Result<()> {
+    let lattice = args.lattice.build()?;
+    let model = args.model.build()?;
+    let sites = (lattice.num_even + lattice.num_odd) as f64;
+    let fields = range(args.h_min, args.h_max, args.h_steps);
+    let mut ensemble = Ensemble::new();
+    for h in fields.iter() {
+        ensemble.push(
+            Simulation::new(lattice.clone(), args.beta)
+                .model(model.field(*h))
+                .monte_carlo(args.mc.monte_carlo()?)
+                .chain(ensemble.len() as u64),
+        );
+    }
+    let mut columns = vec![fields.clone(), Vec::new(), Vec::new()];
+    println!("h magnetization error");
+    for (h, mut measurements) in fields.iter().zip(ensemble.run()?) {
+        let (m, m_sd) = measurements.estimate(Observable::Magnetization);
+        println!("{} {} {}", h, m / sites, m_sd / sites);
+        columns[1].push(m / sites);
+        columns[2].push(m_sd / sites);
+    }
+    if let Some(output) = args.output {
+        write_csv(output, &columns)?;
+    }
+    Ok(())
+}
+
+fn thermalize_trace(args: TraceArgs) -> io::Result<()> {
+    let rng = &mut chain_rng(args.seed, 0);
+    let mut s = State::new(&args.lattice.build()?, 10, rng);
+    let mut ns = Vec::new();
+    let mut ms = Vec::new();
+    for _ in 0..args.steps {
+        s.diagonal_update(args.beta, rng);
+        s.off_diagonal_update(args.nloop, rng);
+        while s.path.len() < s.n * 10 / 8 {
+            let _ = s.path.push(None);
+        }
+        ns.push(s.n as f64);
+        ms.push(s.path.len() as f64);
+    }
+    write_csv(args.output, &[ns, ms])?;
+    Ok(())
+}
+
+fn ed(args: EdArgs) -> io::Result<()> {
+    let lattice = args.lattice.build()?;
+    let mut columns = vec![Vec::new(); 6];
+    if args.ground_state {
+        let g = ground_state_with_model(&lattice, &args.model.build()?);
+        println!("energy = {}", g.energy);
+        println!("staggered magnetization = {}", g.staggered_magnetization);
+        columns = vec![vec![g.energy], vec![g.staggered_magnetization]];
+    } else {
+        let ed = ExactDiagonalization::with_model(&lattice, &args.model.build()?);
+        println!("beta energy specific_heat staggered_magnetization uniform_susceptibility staggered_susceptibility");
+        for beta in args.betas.iter() {
+            let t = ed.thermodynamics(*beta);
+            let row = [
+                *beta,
+                t.energy,
+                t.specific_heat,
+                t.staggered_magnetization,
+                t.uniform_susceptibility,
+                t.staggered_susceptibility,
+            ];
+            println!("{:?}", row);
+            for (column, value) in columns.iter_mut().zip(row) {
+                column.push(value);
+            }
+        }
+    }
+    if let Some(output) = args.output {
+        write_csv(output, &columns)?;
+    }
+    Ok(())
+}
+
+fn analyze(args: AnalyzeArgs) -> io::Result<()> {
+    if args.bin_size == 0 {
+        return Err(invalid_input("bin_size must be positive"));
+    }
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .from_path(&args.input)?;
+    let mut columns: Vec<Vec<f64>> = Vec::new();
+    for record in reader.records() {
+        let record = record?;
+        columns.resize(record.len(), Vec::new());
+        for (column, field) in columns.iter_mut().zip(record.iter()) {
+            column.push(
+                field
+                    .trim()
+                    .parse()
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?,
+            );
+        }
+    }
+    let rng = &mut chain_rng(args.seed, 0);
+    for (i, column) in columns.iter().enumerate() {
+        let binned = stats::bin(column, args.bin_size);
+        let (mean, sd) = stats::bootstrap(&binned, args.bootstraps, rng);
+        println!("column {}: {} +- {}", i, mean, sd);
+    }
+    Ok(())
+}
@@ -0,0 +1,312 @@
+use crate::ensemble::Ensemble;
+use crate::geometry::{bilayer, cubic, honeycomb, ladder, square};
+use crate::graph::Graph;
+use crate::invalid;
+use crate::lattice::{make_jq_latice, make_latice, Boundary, Lattice};
+use crate::model::Model;
+use crate::simulation::{MonteCarlo, Observable, Simulation};
+use crate::stats::{range, write_csv};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Values of a swept parameter: a single value, an explicit list, or
+/// `range(min, max, steps)`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum Grid {
+    Value(f64),
+    Values(Vec<f64>),
+    Range { min: f64, max: f64, steps: usize },
+}
+
+impl Grid {
+    pub fn values(&self) -> Vec<f64> {
+        match self {
+            Grid::Value(v) => vec![*v],
+            Grid::Values(vs) => vs.clone(),
+            Grid::Range { min, max, steps } => range(*min, *max, *steps),
+        }
+    }
+}
+
+/// Lattice of linear size L, where one kind of bond takes the swept coupling
+/// j1 and all others coupling 1. Every geometry but the graph is periodic.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Geometry {
+    /// L x L torus where every other row of vertical bonds has coupling j1
+    ColumnarDimer,
+    /// L x L torus with j1 on the vertical bonds
+    Square,
+    /// Two-leg ladder of L rungs with j1 on the rungs
+    Ladder,
+    /// Two L x L tori with j1 between the layers
+    Bilayer,
+    /// Honeycomb of L x L unit cells with j1 on the bonds within the cells
+    Honeycomb,
+    /// L x L x L torus with j1 on the bonds along z
+    Cubic,
+    /// Edge list read by [`Graph::load`], where the bonds of class `j1`
+    /// without a coupling of their own take j1. The lattice is the same for
+    /// every size, which then only sets `beta_per_length`.
+    Graph(PathBuf),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct LatticeConfig {
+    pub geometry: Geometry,
+    /// Linear sizes L of the lattices
+    pub sizes: Vec<usize>,
+}
+
+impl LatticeConfig {
+    /// The lattice of size `L`, with the plaquette term `q` if it is nonzero.
+    pub fn build(&self, L: usize, j1: f64, q: f64) -> io::Result<Lattice> {
+        let periodic = Boundary::Periodic;
+        match &self.geometry {
+            Geometry::ColumnarDimer if q == 0.0 => make_latice(L, L, j1).map_err(invalid),
+            Geometry::ColumnarDimer => make_jq_latice(L, L, j1, q).map_err(invalid),
+            Geometry::Square => square(L, L, [periodic; 2])
+                .map_err(invalid)?
+                .coupling("y", j1)
+                .build()
+                .map_err(invalid),
+            Geometry::Ladder => ladder(L, 2, periodic)
+                .map_err(invalid)?
+                .coupling("rung", j1)
+                .build()
+                .map_err(invalid),
+            Geometry::Bilayer => bilayer(L, L, [periodic; 2])
+                .map_err(invalid)?
+                .coupling("interlayer", j1)
+                .build()
+                .map_err(invalid),
+            Geometry::Honeycomb => honeycomb(L, L, [periodic; 2])
+                .map_err(invalid)?
+                .coupling("z", j1)
+                .build()
+                .map_err(invalid),
+            Geometry::Cubic => cubic(L, L, L, [periodic; 3])
+                .map_err(invalid)?
+                .coupling("z", j1)
+                .build()
+                .map_err(invalid),
+            Geometry::Graph(path) => {
+                let mut graph = Graph::load(path)?;
+                graph.classes.insert("j1".to_string(), j1);
+                graph.constructor()?.build().map_err(invalid)
+            }
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct ModelConfig {
+    pub j1: Grid,
+    /// Spin of every site
+    pub spin: f64,
+    /// XXZ anisotropy, 1 is the Heisenberg model
+    pub delta: f64,
+    pub epsilon: f64,
+    /// Plaquette coupling of the J-Q model
+    pub q: f64,
+    /// Uniform longitudinal field
+    pub field: f64,
+    /// Longitudinal field added on even and subtracted on odd sites
+    pub staggered_field: f64,
+}
+
+impl Default for ModelConfig {
+    fn default() -> ModelConfig {
+        ModelConfig {
+            j1: Grid::Value(1.0),
+            spin: 0.5,
+            delta: 1.0,
+            epsilon: 0.0,
+            q: 0.0,
+            field: 0.0,
+            staggered_field: 0.0,
+        }
+    }
+}
+
+impl ModelConfig {
+    /// The model at every point, see [`Model::unsupported`] for the checks
+    /// [`RunConfig::validate`] applies to it.
+    pub fn model(&self) -> Model {
+        Model {
+            spin: self.spin,
+            delta: self.delta,
+            epsilon: self.epsilon,
+            field: self.field,
+            staggered_field: self.staggered_field,
+            ..Model::default()
+        }
+    }
+}
+
+/// Exactly one of `betas` and `beta_per_length` must be given.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct SweepConfig {
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub betas: Vec<f64>,
+    /// beta = factor * L
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub beta_per_length: Option<f64>,
+}
+
+impl SweepConfig {
+    pub fn betas(&self, L: usize) -> Vec<f64> {
+        match self.beta_per_length {
+            Some(factor) => vec![factor * L as f64],
+            None => self.betas.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Toml,
+    Json,
+}
+
+/// A simulation campaign that can be checked into version control.
+///
+/// Executing it writes `results.csv` to `output_dir` with one row per chain:
+/// `L, beta, j1` followed by mean and error of every entry of `observables`.
+/// The resolved configuration, with every default filled in, is written next
+/// to it as `config.toml` for provenance.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct RunConfig {
+    pub output_dir: PathBuf,
+    pub lattice: LatticeConfig,
+    #[serde(default)]
+    pub model: ModelConfig,
+    pub sweep: SweepConfig,
+    #[serde(default)]
+    pub monte_carlo: MonteCarlo,
+    #[serde(default = "all_observables")]
+    pub observables: Vec<Observable>,
+    /// Checkpoint every chain into `output_dir/checkpoints` and resume the ones present
+    #[serde(default)]
+    pub checkpoint: bool,
+}
+
+fn all_observables() -> Vec<Observable> {
+    Observable::ALL.to_vec()
+}
+
+fn format_of(path: &Path) -> io::Result<Format> {
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("toml") => Ok(Format::Toml),
+        Some("json") => Ok(Format::Json),
+        _ => Err(invalid(format!(
+            "{} is neither a .toml nor a .json file",
+            path.display()
+        ))),
+    }
+}
+
+impl RunConfig {
+    pub fn load(path: &Path) -> io::Result<RunConfig> {
+        let text = fs::read_to_string(path)?;
+        let config: RunConfig = match format_of(path)? {
+            Format::Toml => toml::from_str(&text).map_err(invalid)?,
+            Format::Json => serde_json::from_str(&text)?,
+        };
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn validate(&self) -> io::Result<()> {
+        if self.sweep.betas.is_empty() == self.sweep.beta_per_length.is_none() {
+            return Err(invalid(
+                "sweep needs exactly one of betas and beta_per_length",
+            ));
+        }
+        if self.lattice.sizes.is_empty() {
+            return Err(invalid("lattice.sizes is empty"));
+        }
+        if self.monte_carlo.bin_size == 0 {
+            return Err(invalid("monte_carlo.bin_size must be positive"));
+        }
+        let j1s = self.model.j1.values();
+        if j1s.is_empty() {
+            return Err(invalid("model.j1 has no values"));
+        }
+        if let Some(j1) = j1s.iter().find(|j1| !j1.is_finite()) {
+            return Err(invalid(format!("model.j1 {} is not finite", j1)));
+        }
+        if let Some(reason) = self.model.model().unsupported() {
+            return Err(invalid(reason));
+        }
+        if !self.model.q.is_finite() {
+            return Err(invalid(format!("model.q {} is not finite", self.model.q)));
+        }
+        if self.model.q != 0.0 && self.lattice.geometry != Geometry::ColumnarDimer {
+            return Err(invalid(
+                "the plaquette term q needs the columnar-dimer geometry",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Writes the configuration to `path`, in the format given by its extension.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let text = match format_of(path)? {
+            Format::Toml => toml::to_string_pretty(self).map_err(invalid)?,
+            Format::Json => serde_json::to_string_pretty(self)?,
+        };
+        fs::write(path, text)
+    }
+
+    pub fn execute(&self) -> io::Result<()> {
+        fs::create_dir_all(&self.output_dir)?;
+        self.save(&self.output_dir.join("config.toml"))?;
+        let checkpoint_dir = self.output_dir.join("checkpoints");
+        if self.checkpoint {
+            fs::create_dir_all(&checkpoint_dir)?;
+        }
+
+        let mut ensemble = Ensemble::new();
+        let mut points = Vec::new();
+        for L in self.lattice.sizes.iter() {
+            for beta in self.sweep.betas(*L) {
+                for j1 in self.model.j1.values() {
+                    let mut simulation =
+                        Simulation::new(self.lattice.build(*L, j1, self.model.q)?, beta)
+                            .model(self.model.model())
+                            .monte_carlo(self.monte_carlo.clone())
+                            .chain(points.len() as u64);
+                    if self.checkpoint {
+                        let path = checkpoint_dir.join(format!("L{}_beta{}_j{}.json", L, beta, j1));
+                        simulation = simulation.checkpoint(path, 1000);
+                    }
+                    ensemble.push(simulation);
+                    points.push([*L as f64, beta, j1]);
+                }
+            }
+        }
+
+        let mut columns = vec![Vec::new(); 3 + 2 * self.observables.len()];
+        for (point, mut measurements) in points.into_iter().zip(ensemble.run()?) {
+            let mut row = point.to_vec();
+            for observable in self.observables.iter() {
+                let (mean, sd) = measurements.estimate(*observable);
+                row.push(mean);
+                row.push(sd);
+            }
+            for (column, value) in columns.iter_mut().zip(row) {
+                column.push(value);
+            }
+        }
+        write_csv(self.output_dir.join("results.csv"), &columns)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,454 @@
+use crate::lattice::Lattice;
+use crate::model::Model;
+use crate::tfim::TransverseIsing;
+
+/// Thermal expectation values of the XXZ model of [`Model`] at a single beta.
+///
+/// All quantities are totals over the lattice (not per site), matching the
+/// estimators returned by `State::sample`.
+#[derive(Clone, Copy, Debug)]
+pub struct Thermodynamics {
+    pub energy: f64,
+    pub specific_heat: f64,
+    /// `<sum_i S^z_i>`
+    pub magnetization: f64,
+    /// `<|sum_i (-1)^i S^z_i|>`, even sites counted positive.
+    pub staggered_magnetization: f64,
+    pub uniform_susceptibility: f64,
+    pub staggered_susceptibility: f64,
+}
+
+/// Diagonalized block of the hamiltonian, of fixed total S^z where it is conserved.
+struct Sector {
+    /// basis states, digit `i` in base `levels` is the level `S^z + S` of site `i`
+    basis: Vec<u64>,
+    eigenvalues: Vec<f64>,
+    /// `eigenvectors[a * dim + k]` is component `a` of eigenvector `k`
+    eigenvectors: Vec<f64>,
+}
+
+/// Full spectrum of the XXZ model, plus the plaquettes of the lattice, on a
+/// small lattice.
+///
+/// Even sites are numbered `0..num_even` and odd sites follow them, so the
+/// hamiltonian is built from exactly the same edges the SSE samples.
+pub struct ExactDiagonalization {
+    /// Number of S^z states of a site.
+    levels: usize,
+    /// Subtracted from a level to give `S^z`, zero for bosons.
+    offset: f64,
+    num_even: usize,
+    num_sites: usize,
+    sectors: Vec<Sector>,
+}
+
+impl ExactDiagonalization {
+    /// The Heisenberg model, see [`ExactDiagonalization::with_model`].
+    pub fn new(lattice: &Lattice) -> ExactDiagonalization {
+        ExactDiagonalization::with_model(lattice, &Model::default())
+    }
+
+    pub fn with_model(lattice: &Lattice, model: &Model) -> ExactDiagonalization {
+        let num_sites = lattice.num_even + lattice.num_odd;
+        let levels = model.levels();
+        // the largest sector is stored as a dense matrix
+        assert!(
+            (levels as u64).pow(num_sites as u32) <= 1 << 14,
+            "Lattice too large for exact diagonalization"
+        );
+        let bonds = bonds(lattice);
+        let mut sectors = Vec::new();
+        for total in 0..=(levels - 1) * num_sites {
+            let basis = sector_basis(levels, num_sites, total);
+            let dim = basis.len();
+            let mut h = vec![0.0; dim * dim];
+            for (a, b, value) in sector_hamiltonian(&bonds, model, lattice, &basis) {
+                h[b * dim + a] += value;
+            }
+            let (eigenvalues, eigenvectors) = symmetric_eigen(h, dim);
+            sectors.push(Sector {
+                basis,
+                eigenvalues,
+                eigenvectors,
+            });
+        }
+        ExactDiagonalization {
+            levels,
+            offset: -model.sz(0),
+            num_even: lattice.num_even,
+            num_sites,
+            sectors,
+        }
+    }
+
+    /// The transverse-field Ising model of `model`, diagonalized in the full
+    /// basis since it does not conserve S^z.
+    ///
+    /// The uniform susceptibility of [`Thermodynamics`] is then only the
+    /// fluctuation `beta (<M^2> - <M>^2)`, not the response to a field.
+    pub fn transverse_ising(lattice: &Lattice, model: &TransverseIsing) -> ExactDiagonalization {
+        let num_sites = lattice.num_even + lattice.num_odd;
+        assert!(
+            num_sites <= 10,
+            "Lattice too large for exact diagonalization"
+        );
+        let dim = 1 << num_sites;
+        let mut h = vec![0.0; dim * dim];
+        for state in 0..dim {
+            for &(i, j, coupling) in bonds(lattice).iter() {
+                let parallel = (state >> i & 1) == (state >> j & 1);
+                h[state * dim + state] += if parallel { coupling } else { -coupling } / 4.0;
+            }
+            for site in 0..num_sites {
+                h[(state ^ 1 << site) * dim + state] -= model.field / 2.0;
+            }
+        }
+        let (eigenvalues, eigenvectors) = symmetric_eigen(h, dim);
+        ExactDiagonalization {
+            levels: 2,
+            offset: 0.5,
+            num_even: lattice.num_even,
+            num_sites,
+            sectors: vec![Sector {
+                basis: (0..dim as u64).collect(),
+                eigenvalues,
+                eigenvectors,
+            }],
+        }
+    }
+
+    pub fn ground_state_energy(&self) -> f64 {
+        self.sectors
+            .iter()
+            .flat_map(|s| s.eigenvalues.iter())
+            .fold(f64::INFINITY, |a, &b| a.min(b))
+    }
+
+    pub fn thermodynamics(&self, beta: f64) -> Thermodynamics {
+        let e0 = self.ground_state_energy();
+        let mut z = 0.0;
+        let mut energy = 0.0;
+        let mut energy2 = 0.0;
+        let mut m = 0.0;
+        let mut m2 = 0.0;
+        let mut sm = 0.0;
+        let mut chi_s = 0.0;
+        for sector in self.sectors.iter() {
+            let dim = sector.basis.len();
+            let staggered: Vec<f64> = sector
+                .basis
+                .iter()
+                .map(|&s| staggered(self.levels, self.offset, self.num_even, self.num_sites, s))
+                .collect();
+            let weights: Vec<f64> = sector
+                .eigenvalues
+                .iter()
+                .map(|e| (-beta * (e - e0)).exp())
+                .collect();
+            // <k|M_s|l> for all eigenvector pairs of the sector
+            let mut ms = vec![0.0; dim * dim];
+            for (row, staggered_a) in sector.eigenvectors.chunks(dim).zip(staggered.iter()) {
+                for k in 0..dim {
+                    let vk = row[k] * staggered_a;
+                    for l in 0..dim {
+                        ms[k * dim + l] += vk * row[l];
+                    }
+                }
+            }
+            for k in 0..dim {
+                let (ek, wk) = (sector.eigenvalues[k], weights[k]);
+                z += wk;
+                energy += wk * ek;
+                energy2 += wk * ek * ek;
+                for (a, &state) in sector.basis.iter().enumerate() {
+                    let p = sector.eigenvectors[a * dim + k].powi(2);
+                    let magnetization = (0..self.num_sites)
+                        .map(|site| sz(self.levels, self.offset, state, site))
+                        .sum::<f64>();
+                    m += wk * p * magnetization;
+                    m2 += wk * p * magnetization * magnetization;
+                    sm += wk * p * staggered[a].abs();
+                }
+                for l in 0..dim {
+                    let el = sector.eigenvalues[l];
+                    // Kubo integral of e^{-beta E_k} e^{tau (E_k - E_l)} over tau in [0, beta]
+                    let kubo = if (ek - el).abs() < 1e-10 {
+                        beta * wk
+                    } else {
+                        (wk - weights[l]) / (el - ek)
+                    };
+                    chi_s += kubo * ms[k * dim + l].powi(2);
+                }
+            }
+        }
+        energy /= z;
+        energy2 /= z;
+        m /= z;
+        m2 /= z;
+        Thermodynamics {
+            energy,
+            specific_heat: beta * beta * (energy2 - energy * energy),
+            magnetization: m,
+            staggered_magnetization: sm / z,
+            uniform_susceptibility: beta * (m2 - m * m),
+            staggered_susceptibility: chi_s / z,
+        }
+    }
+}
+
+/// Ground state of a lattice too large for [`ExactDiagonalization`].
+#[derive(Clone, Copy, Debug)]
+pub struct GroundState {
+    pub energy: f64,
+    /// `<|sum_i (-1)^i S^z_i|>` in the ground state.
+    pub staggered_magnetization: f64,
+}
+
+/// Finds the Heisenberg ground state, see [`ground_state_with_model`].
+pub fn ground_state(lattice: &Lattice) -> GroundState {
+    ground_state_with_model(lattice, &Model::default())
+}
+
+/// Finds the ground state with the Lanczos algorithm in the lowest |S^z| sector.
+///
+/// This is the zero temperature limit of [`ExactDiagonalization::thermodynamics`]
+/// and only needs sparse storage, so it handles spin 1/2 lattices of up to ~24 sites.
+/// In the easy-axis regime or in a uniform field the ground state may lie in
+/// another sector.
+pub fn ground_state_with_model(lattice: &Lattice, model: &Model) -> GroundState {
+    let num_sites = lattice.num_even + lattice.num_odd;
+    let levels = model.levels();
+    assert!(
+        (levels as u64).pow(num_sites as u32) <= 1 << 24,
+        "Lattice too large for Lanczos"
+    );
+    let basis = sector_basis(levels, num_sites, (levels - 1) * num_sites / 2);
+    let h = sector_hamiltonian(&bonds(lattice), model, lattice, &basis);
+    let dim = basis.len();
+    let matvec = |v: &[f64]| {
+        let mut w = vec![0.0; dim];
+        for &(a, b, value) in h.iter() {
+            w[b] += value * v[a];
+        }
+        w
+    };
+    let dot = |a: &[f64], b: &[f64]| a.iter().zip(b).map(|(x, y)| x * y).sum::<f64>();
+
+    // deterministic start vector with overlap on every basis state
+    let mut v: Vec<f64> = (0..dim).map(|i| 1.0 + (i as f64).sin() * 0.5).collect();
+    let norm = dot(&v, &v).sqrt();
+    v.iter_mut().for_each(|x| *x /= norm);
+    let mut vs = vec![v];
+    let mut alphas = Vec::new();
+    let mut betas: Vec<f64> = Vec::new();
+    let mut energy = f64::INFINITY;
+    let mut coefficients = vec![1.0];
+    loop {
+        let k = vs.len() - 1;
+        let mut w = matvec(&vs[k]);
+        alphas.push(dot(&w, &vs[k]));
+        // full reorthogonalization keeps the krylov basis orthonormal
+        for _ in 0..2 {
+            for u in vs.iter() {
+                let overlap = dot(&w, u);
+                w.iter_mut().zip(u).for_each(|(x, y)| *x -= overlap * y);
+            }
+        }
+        let b = dot(&w, &w).sqrt();
+
+        let m = alphas.len();
+        let mut t = vec![0.0; m * m];
+        for i in 0..m {
+            t[i * m + i] = alphas[i];
+            if i + 1 < m {
+                t[i * m + i + 1] = betas[i];
+                t[(i + 1) * m + i] = betas[i];
+            }
+        }
+        let (values, vectors) = symmetric_eigen(t, m);
+        let lowest = (0..m).fold(0, |a, i| if values[i] < values[a] { i } else { a });
+        let converged = (energy - values[lowest]).abs() < 1e-12;
+        energy = values[lowest];
+        coefficients = (0..m).map(|i| vectors[i * m + lowest]).collect();
+        if converged || b < 1e-10 || m == dim {
+            break;
+        }
+        w.iter_mut().for_each(|x| *x /= b);
+        betas.push(b);
+        vs.push(w);
+    }
+
+    let mut psi = vec![0.0; dim];
+    for (c, u) in coefficients.iter().zip(vs.iter()) {
+        psi.iter_mut().zip(u).for_each(|(x, y)| *x += c * y);
+    }
+    let norm = dot(&psi, &psi);
+    let staggered_magnetization = basis
+        .iter()
+        .zip(psi.iter())
+        .map(|(&s, x)| {
+            x * x * staggered(levels, -model.sz(0), lattice.num_even, num_sites, s).abs()
+        })
+        .sum::<f64>()
+        / norm;
+    GroundState {
+        energy,
+        staggered_magnetization,
+    }
+}
+
+/// Edges as `(site, site, coupling)` with odd sites numbered after the even ones.
+fn bonds(lattice: &Lattice) -> Vec<(usize, usize, f64)> {
+    lattice
+        .edges
+        .iter()
+        .map(|e| (e.even.0, lattice.num_even + e.odd.0, e.coupling))
+        .collect()
+}
+
+/// Level of `site` in the basis state `state`.
+fn level(levels: usize, state: u64, site: usize) -> u8 {
+    (state / (levels as u64).pow(site as u32) % levels as u64) as u8
+}
+
+/// `S^z` of `site` in the basis state `state`, its level less `offset`.
+fn sz(levels: usize, offset: f64, state: u64, site: usize) -> f64 {
+    level(levels, state, site) as f64 - offset
+}
+
+/// All states of `num_sites` sites whose levels add up to `total`, in increasing order.
+fn sector_basis(levels: usize, num_sites: usize, total: usize) -> Vec<u64> {
+    (0..(levels as u64).pow(num_sites as u32))
+        .filter(|&state| {
+            (0..num_sites)
+                .map(|site| level(levels, state, site) as usize)
+                .sum::<usize>()
+                == total
+        })
+        .collect()
+}
+
+/// Nonzero matrix elements `(from, to, value)` of the hamiltonian within one sector.
+fn sector_hamiltonian(
+    bonds: &[(usize, usize, f64)],
+    model: &Model,
+    lattice: &Lattice,
+    basis: &[u64],
+) -> Vec<(usize, usize, f64)> {
+    let num_sites = lattice.num_even + lattice.num_odd;
+    let levels = model.levels();
+    assert!(
+        lattice.plaquettes.is_empty() || levels == 2,
+        "Plaquette operators need spin 1/2"
+    );
+    let mut entries = Vec::new();
+    for (a, &state) in basis.iter().enumerate() {
+        let mut diagonal = 0.0;
+        for site in 0..num_sites {
+            diagonal += model.site_energy(level(levels, state, site), site < lattice.num_even);
+        }
+        for &(i, j, coupling) in bonds.iter() {
+            let (level_i, level_j) = (level(levels, state, i), level(levels, state, j));
+            diagonal += coupling * model.delta * model.sz(level_i) * model.sz(level_j);
+            // S^+_i S^-_j and S^-_i S^+_j
+            let (unit_i, unit_j) = ((levels as u64).pow(i as u32), (levels as u64).pow(j as u32));
+            if (level_i as usize) < levels - 1 && level_j > 0 {
+                let b = basis.binary_search(&(state + unit_i - unit_j)).unwrap();
+                let value = model.raise(level_i) * model.raise(level_j - 1);
+                entries.push((a, b, model.off_diagonal(coupling) * value));
+            }
+            if level_i > 0 && (level_j as usize) < levels - 1 {
+                let b = basis.binary_search(&(state - unit_i + unit_j)).unwrap();
+                let value = model.raise(level_i - 1) * model.raise(level_j);
+                entries.push((a, b, model.off_diagonal(coupling) * value));
+            }
+        }
+        // P |up down> = (|up down> - |down up>) / 2 and P vanishes on parallel spins
+        for plaquette in lattice.plaquettes.iter() {
+            let flips = plaquette
+                .edges
+                .map(|e| 1u64 << e.even.0 | 1 << (lattice.num_even + e.odd.0));
+            if flips.iter().any(|&f| (state & f).count_ones() != 1) {
+                continue;
+            }
+            for (flip, sign) in [
+                (0, 1.0),
+                (flips[0], -1.0),
+                (flips[1], -1.0),
+                (flips[0] ^ flips[1], 1.0),
+            ] {
+                let value = -plaquette.coupling * sign / 4.0;
+                if flip == 0 {
+                    diagonal += value;
+                } else {
+                    let b = basis.binary_search(&(state ^ flip)).unwrap();
+                    entries.push((a, b, value));
+                }
+            }
+        }
+        entries.push((a, a, diagonal));
+    }
+    entries
+}
+
+fn staggered(levels: usize, offset: f64, num_even: usize, num_sites: usize, state: u64) -> f64 {
+    (0..num_sites)
+        .map(|site| {
+            if site < num_even {
+                sz(levels, offset, state, site)
+            } else {
+                -sz(levels, offset, state, site)
+            }
+        })
+        .sum()
+}
+
+/// Cyclic Jacobi diagonalization of the symmetric row major `n x n` matrix `a`.
+///
+/// Returns the eigenvalues and the eigenvectors stored as columns.
+fn symmetric_eigen(mut a: Vec<f64>, n: usize) -> (Vec<f64>, Vec<f64>) {
+    let mut v = vec![0.0; n * n];
+    for i in 0..n {
+        v[i * n + i] = 1.0;
+    }
+    let scale: f64 = a.iter().map(|x| x * x).sum::<f64>().max(f64::MIN_POSITIVE);
+    for _ in 0..100 {
+        let mut off = 0.0;
+        for p in 0..n {
+            for q in p + 1..n {
+                off += a[p * n + q] * a[p * n + q];
+            }
+        }
+        if off <= 1e-30 * scale {
+            break;
+        }
+        for p in 0..n {
+            for q in p + 1..n {
+                let apq = a[p * n + q];
+                if apq == 0.0 {
+                    continue;
+                }
+                let theta = (a[q * n + q] - a[p * n + p]) / (2.0 * apq);
+                let t = theta.signum() / (theta.abs() + (theta * theta + 1.0).sqrt());
+                let c = 1.0 / (t * t + 1.0).sqrt();
+                let s = t * c;
+                for k in 0..n {
+                    let (akp, akq) = (a[k * n + p], a[k * n + q]);
+                    a[k * n + p] = c * akp - s * akq;
+                    a[k * n + q] = s * akp + c * akq;
+                }
+                for k in 0..n {
+                    let (apk, aqk) = (a[p * n + k], a[q * n + k]);
+                    a[p * n + k] = c * apk - s * aqk;
+                    a[q * n + k] = s * apk + c * aqk;
+                }
+                for k in 0..n {
+                    let (vkp, vkq) = (v[k * n + p], v[k * n + q]);
+                    v[k * n + p] = c * vkp - s * vkq;
+                    v[k * n + q] = s * vkp + c * vkq;
+                }
+            }
+        }
+    }
+    ((0..n).map(|i| a[i * n + i]).collect(), v)
+}
@@ -0,0 +1,85 @@
+use crate::simulation::{Measurements, Simulation};
+use rayon::prelude::*;
+use std::io;
+
+/// Independent Markov chains run on a thread pool.
+///
+/// Every point of a sweep is expanded into its [`Simulation::replicas`], all
+/// chains are distributed over the pool, and the replicas of each point are
+/// merged again with [`Measurements::merge`]. Chains only depend on their own
+/// seed and stream, so the results do not depend on the number of threads.
+///
+/// ```no_run
+/// use montecarlo::ensemble::Ensemble;
+/// use montecarlo::lattice::make_latice;
+/// use montecarlo::simulation::{MonteCarlo, Observable, Simulation};
+///
+/// let monte_carlo = MonteCarlo { chains: 8, ..MonteCarlo::default() };
+/// let mut ensemble = Ensemble::new();
+/// for (chain, j1) in [0.5, 1.0, 1.5].into_iter().enumerate() {
+///     ensemble.push(
+///         Simulation::new(make_latice(4, 4, j1).unwrap(), 8.0)
+///             .monte_carlo(monte_carlo.clone())
+///             .chain(chain as u64),
+///     );
+/// }
+/// for mut point in ensemble.run().unwrap() {
+///     println!("{:?}", point.estimate(Observable::Energy));
+/// }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Ensemble {
+    points: Vec<Simulation>,
+    threads: Option<usize>,
+}
+
+impl Ensemble {
+    pub fn new() -> Ensemble {
+        Ensemble::default()
+    }
+    pub fn push(&mut self, simulation: Simulation) {
+        self.points.push(simulation);
+    }
+    /// Overrides `monte_carlo.threads` of the points, 0 uses every core.
+    pub fn threads(mut self, threads: usize) -> Ensemble {
+        self.threads = Some(threads);
+        self
+    }
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    /// Runs every chain and returns the merged measurements of each point, in
+    /// the order the points were pushed.
+    pub fn run(&self) -> io::Result<Vec<Measurements>> {
+        let threads = self.threads.unwrap_or_else(|| {
+            self.points
+                .iter()
+                .map(|point| point.monte_carlo.threads)
+                .max()
+                .unwrap_or(0)
+        });
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .map_err(io::Error::other)?;
+        let replicas: Vec<Vec<Simulation>> =
+            self.points.iter().map(|point| point.replicas()).collect();
+        let chains: Vec<&Simulation> = replicas.iter().flatten().collect();
+        let mut results = pool
+            .install(|| {
+                chains
+                    .par_iter()
+                    .map(|chain| chain.run_chain())
+                    .collect::<io::Result<Vec<Measurements>>>()
+            })?
+            .into_iter();
+        Ok(replicas
+            .iter()
+            .map(|point| Measurements::merge(results.by_ref().take(point.len()).collect()))
+            .collect())
+    }
+}
@@ -0,0 +1,482 @@
+//! Common bipartite lattices as [`LatticeConstructor`]s.
+//!
+//! Every builder places its nodes at their real-space positions, with unit
+//! nearest-neighbour distance, and labels every bond with an [`EdgeType`].
+//! All bonds start with coupling 1, set another one per type with
+//! [`LatticeConstructor::coupling`]:
+//!
+//! ```
+//! use montecarlo::geometry::ladder;
+//! use montecarlo::lattice::Boundary;
+//!
+//! let lattice = ladder(8, 2, Boundary::Periodic)
+//!     .unwrap()
+//!     .coupling("rung", 0.5)
+//!     .build()
+//!     .unwrap();
+//! ```
+//!
+//! Every axis of a builder closes with its own [`Boundary`], which the built
+//! [`crate::lattice::Lattice`] records. The builders check up front that the
+//! lengths fit the boundaries: on all lattices but the honeycomb a periodic
+//! axis needs an even length to stay bipartite, and a twisted axis needs an
+//! even sum of its length and shift.
+//!
+//! Other lattices are described by a [`UnitCell`], repeated along its
+//! lattice vectors.
+//!
+//! [`EdgeType`]: crate::lattice::EdgeType
+
+use crate::lattice::{Boundary, CellSite, EdgeType, LatticeConstructor, LatticeError};
+
+/// A builder's constructor, or why its lengths do not fit its boundaries.
+pub type Geometry<T> = Result<LatticeConstructor<T>, LatticeError<T>>;
+
+/// Which bonds of the square lattice [`square_dimers`] marks as `"dimer"`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DimerPattern {
+    /// Vertical bonds leaving even rows, the pattern of [`crate::lattice::make_latice`].
+    Columnar,
+    /// Vertical bonds leaving even rows in even columns and odd rows in odd
+    /// columns, so neighbouring columns of dimers are shifted.
+    Staggered,
+    /// The four bonds around every square whose lower left corner has even
+    /// coordinates, forming isolated 2x2 plaquettes.
+    Plaquette,
+}
+
+/// `length` sites in a row with bonds of type `"bond"`.
+pub fn chain(length: usize, boundary: Boundary) -> Geometry<usize> {
+    validate([length], [boundary], true)?;
+    let positions = (0..length).map(|x| [x as f64, 0.0, 0.0]).collect();
+    let mut constructor = LatticeConstructor::with_positions((0..length).collect(), positions)
+        .with_boundaries(pad([boundary]));
+    for x in 0..length {
+        if let Some([right]) = step([x], 0, 1, [length], [boundary]) {
+            constructor.add_typed_edge(x, right, "bond", 1.0, [1, 0, 0]);
+        }
+    }
+    Ok(constructor)
+}
+
+/// `legs` chains of `length` sites closed by `boundary`, nodes `(x, leg)`,
+/// with bonds of type `"leg"` along the chains and `"rung"` between
+/// neighbouring chains.
+pub fn ladder(length: usize, legs: usize, boundary: Boundary) -> Geometry<(usize, usize)> {
+    let (lengths, boundaries) = ([length, legs], [boundary, Boundary::Open]);
+    validate(lengths, boundaries, true)?;
+    let (nodes, positions) = grid(length, legs);
+    let mut constructor =
+        LatticeConstructor::with_positions(nodes, positions).with_boundaries(pad(boundaries));
+    for x in 0..length {
+        for leg in 0..legs {
+            if let Some([right, _]) = step([x, leg], 0, 1, lengths, boundaries) {
+                constructor.add_typed_edge((x, leg), (right, leg), "leg", 1.0, [1, 0, 0]);
+            }
+            if leg + 1 < legs {
+                constructor.add_typed_edge((x, leg), (x, leg + 1), "rung", 1.0, [0, 0, 0]);
+            }
+        }
+    }
+    Ok(constructor)
+}
+
+/// The `width x height` square lattice, nodes `(x, y)`, with bonds of type
+/// `"x"` and `"y"`.
+pub fn square(width: usize, height: usize, boundaries: [Boundary; 2]) -> Geometry<(usize, usize)> {
+    let lengths = [width, height];
+    validate(lengths, boundaries, true)?;
+    let (nodes, positions) = grid(width, height);
+    let mut constructor =
+        LatticeConstructor::with_positions(nodes, positions).with_boundaries(pad(boundaries));
+    for x in 0..width {
+        for y in 0..height {
+            if let Some([x2, y2]) = step([x, y], 0, 1, lengths, boundaries) {
+                constructor.add_typed_edge((x, y), (x2, y2), "x", 1.0, [1, 0, 0]);
+            }
+            if let Some([x2, y2]) = step([x, y], 1, 1, lengths, boundaries) {
+                constructor.add_typed_edge((x, y), (x2, y2), "y", 1.0, [0, 1, 0]);
+            }
+        }
+    }
+    Ok(constructor)
+}
+
+/// The `width x height` square lattice with the bonds of `pattern` of type
+/// `"dimer"` and all other bonds of type `"bond"`.
+pub fn square_dimers(
+    width: usize,
+    height: usize,
+    pattern: DimerPattern,
+    boundaries: [Boundary; 2],
+) -> Geometry<(usize, usize)> {
+    let lengths = [width, height];
+    validate(lengths, boundaries, true)?;
+    let (nodes, positions) = grid(width, height);
+    let mut constructor =
+        LatticeConstructor::with_positions(nodes, positions).with_boundaries(pad(boundaries));
+    let label = |dimer: bool| if dimer { "dimer" } else { "bond" };
+    for x in 0..width {
+        for y in 0..height {
+            let (horizontal, vertical) = match pattern {
+                DimerPattern::Columnar => (false, y % 2 == 0),
+                DimerPattern::Staggered => (false, y % 2 == x % 2),
+                DimerPattern::Plaquette => (x % 2 == 0, y % 2 == 0),
+            };
+            if let Some([x2, y2]) = step([x, y], 0, 1, lengths, boundaries) {
+                constructor.add_typed_edge((x, y), (x2, y2), label(horizontal), 1.0, [1, 0, 0]);
+            }
+            if let Some([x2, y2]) = step([x, y], 1, 1, lengths, boundaries) {
+                constructor.add_typed_edge((x, y), (x2, y2), label(vertical), 1.0, [0, 1, 0]);
+            }
+        }
+    }
+    Ok(constructor)
+}
+
+/// Two `width x height` square lattices, nodes `(x, y, layer)`, with bonds
+/// of type `"layer"` within and `"interlayer"` between them.
+pub fn bilayer(
+    width: usize,
+    height: usize,
+    boundaries: [Boundary; 2],
+) -> Geometry<(usize, usize, usize)> {
+    let lengths = [width, height];
+    validate(lengths, boundaries, true)?;
+    let mut nodes = Vec::new();
+    let mut positions = Vec::new();
+    for layer in 0..2 {
+        for x in 0..width {
+            for y in 0..height {
+                nodes.push((x, y, layer));
+                positions.push([x as f64, y as f64, layer as f64]);
+            }
+        }
+    }
+    let mut constructor =
+        LatticeConstructor::with_positions(nodes, positions).with_boundaries(pad(boundaries));
+    for layer in 0..2 {
+        for x in 0..width {
+            for y in 0..height {
+                let site = (x, y, layer);
+                if let Some([x2, y2]) = step([x, y], 0, 1, lengths, boundaries) {
+                    constructor.add_typed_edge(site, (x2, y2, layer), "layer", 1.0, [1, 0, 0]);
+                }
+                if let Some([x2, y2]) = step([x, y], 1, 1, lengths, boundaries) {
+                    constructor.add_typed_edge(site, (x2, y2, layer), "layer", 1.0, [0, 1, 0]);
+                }
+            }
+        }
+    }
+    for x in 0..width {
+        for y in 0..height {
+            constructor.add_typed_edge((x, y, 0), (x, y, 1), "interlayer", 1.0, [0, 0, 0]);
+        }
+    }
+    Ok(constructor)
+}
+
+/// The `lx x ly x lz` simple cubic lattice, nodes `(x, y, z)`, with bonds of
+/// type `"x"`, `"y"` and `"z"`.
+pub fn cubic(
+    lx: usize,
+    ly: usize,
+    lz: usize,
+    boundaries: [Boundary; 3],
+) -> Geometry<(usize, usize, usize)> {
+    let lengths = [lx, ly, lz];
+    validate(lengths, boundaries, true)?;
+    let mut nodes = Vec::new();
+    let mut positions = Vec::new();
+    for x in 0..lx {
+        for y in 0..ly {
+            for z in 0..lz {
+                nodes.push((x, y, z));
+                positions.push([x as f64, y as f64, z as f64]);
+            }
+        }
+    }
+    let mut constructor =
+        LatticeConstructor::with_positions(nodes, positions).with_boundaries(boundaries);
+    for x in 0..lx {
+        for y in 0..ly {
+            for z in 0..lz {
+                for (axis, edge_type) in ["x", "y", "z"].into_iter().enumerate() {
+                    if let Some([x2, y2, z2]) = step([x, y, z], axis, 1, lengths, boundaries) {
+                        let mut displacement = [0; 3];
+                        displacement[axis] = 1;
+                        constructor.add_typed_edge(
+                            (x, y, z),
+                            (x2, y2, z2),
+                            edge_type,
+                            1.0,
+                            displacement,
+                        );
+                    }
+                }
+            }
+        }
+    }
+    Ok(constructor)
+}
+
+/// The honeycomb lattice of `width x height` unit cells spanned by
+/// `a_1 = (√3, 0)` and `a_2 = (√3/2, 3/2)`, nodes `(x, y, basis)` with basis 0
+/// at the origin of the cell and basis 1 above it. The three bonds of every
+/// site are of type `"x"`, `"y"` and `"z"`, the last one within a cell, and
+/// their displacements count unit cells along `a_1` and `a_2`.
+///
+/// The two basis sites are the two sublattices, so any lengths are bipartite.
+pub fn honeycomb(
+    width: usize,
+    height: usize,
+    boundaries: [Boundary; 2],
+) -> Geometry<(usize, usize, usize)> {
+    let lengths = [width, height];
+    validate(lengths, boundaries, false)?;
+    let sqrt3 = 3f64.sqrt();
+    let mut nodes = Vec::new();
+    let mut positions = Vec::new();
+    for x in 0..width {
+        for y in 0..height {
+            let origin = [sqrt3 * (x as f64 + y as f64 / 2.0), 1.5 * y as f64];
+            for basis in 0..2 {
+                nodes.push((x, y, basis));
+                positions.push([origin[0], origin[1] + basis as f64, 0.0]);
+            }
+        }
+    }
+    let mut constructor =
+        LatticeConstructor::with_positions(nodes, positions).with_boundaries(pad(boundaries));
+    for x in 0..width {
+        for y in 0..height {
+            constructor.add_typed_edge((x, y, 0), (x, y, 1), "z", 1.0, [0, 0, 0]);
+            if let Some([x2, y2]) = step([x, y], 1, -1, lengths, boundaries) {
+                constructor.add_typed_edge((x, y, 0), (x2, y2, 1), "y", 1.0, [0, -1, 0]);
+                if let Some([x3, y3]) = step([x2, y2], 0, 1, lengths, boundaries) {
+                    constructor.add_typed_edge((x, y, 0), (x3, y3, 1), "x", 1.0, [1, -1, 0]);
+                }
+            }
+        }
+    }
+    Ok(constructor)
+}
+
+/// Basis sites and the bonds between them, in `D` dimensions, from which
+/// [`UnitCell::repeat`] builds a lattice.
+///
+/// The honeycomb lattice of [`honeycomb`] reads
+///
+/// ```
+/// use montecarlo::geometry::UnitCell;
+/// use montecarlo::lattice::Boundary;
+///
+/// let sqrt3 = 3f64.sqrt();
+/// let lattice = UnitCell::new([[sqrt3, 0.0, 0.0], [sqrt3 / 2.0, 1.5, 0.0]])
+///     .site([0.0, 0.0, 0.0])
+///     .site([0.0, 1.0, 0.0])
+///     .bond(0, 1, [0, 0], "z")
+///     .bond(0, 1, [0, -1], "y")
+///     .bond(0, 1, [1, -1], "x")
+///     .repeat([3, 4], [Boundary::Periodic; 2])
+///     .unwrap()
+///     .build()
+///     .unwrap();
+/// assert_eq!(lattice.edges.len(), 36);
+/// ```
+#[derive(Clone, Debug)]
+pub struct UnitCell<const D: usize> {
+    vectors: [[f64; 3]; D],
+    basis: Vec<[f64; 3]>,
+    bonds: Vec<CellBond<D>>,
+}
+
+#[derive(Clone, Debug)]
+struct CellBond<const D: usize> {
+    from: usize,
+    to: usize,
+    offset: [isize; D],
+    edge_type: EdgeType,
+}
+
+impl<const D: usize> UnitCell<D> {
+    /// An empty cell spanned by the real-space lattice `vectors`.
+    pub fn new(vectors: [[f64; 3]; D]) -> UnitCell<D> {
+        assert!((1..=3).contains(&D), "A unit cell has one to three vectors");
+        UnitCell {
+            vectors,
+            basis: Vec::new(),
+            bonds: Vec::new(),
+        }
+    }
+    /// Adds a basis site at `position` from the origin of the cell.
+    pub fn site(mut self, position: [f64; 3]) -> UnitCell<D> {
+        self.basis.push(position);
+        self
+    }
+    /// Adds a bond of type `edge_type` from basis site `from` to basis site
+    /// `to` of the cell `offset` lattice vectors away, zero within the cell.
+    pub fn bond(
+        mut self,
+        from: usize,
+        to: usize,
+        offset: [isize; D],
+        edge_type: &str,
+    ) -> UnitCell<D> {
+        for site in [from, to] {
+            assert!(site < self.basis.len(), "No basis site {}", site);
+        }
+        self.bonds.push(CellBond {
+            from,
+            to,
+            offset,
+            edge_type: edge_type.into(),
+        });
+        self
+    }
+    /// `counts` copies of the cell along every lattice vector, closed by
+    /// `boundaries`, nodes `(cell, basis)` with all couplings 1.
+    ///
+    /// The built lattice records the [`CellSite`] and the real-space position
+    /// of every site, and measures the displacements of the bonds in lattice
+    /// vectors. Whether the bonds are bipartite is only known once the
+    /// lattice is built.
+    pub fn repeat(
+        &self,
+        counts: [usize; D],
+        boundaries: [Boundary; D],
+    ) -> Geometry<([usize; D], usize)> {
+        validate(counts, boundaries, false)?;
+        let mut cells = vec![[0; D]];
+        for axis in 0..D {
+            cells = cells
+                .into_iter()
+                .flat_map(|cell| {
+                    (0..counts[axis]).map(move |n| {
+                        let mut cell = cell;
+                        cell[axis] = n;
+                        cell
+                    })
+                })
+                .collect();
+        }
+        let mut nodes = Vec::new();
+        let mut positions = Vec::new();
+        let mut sites = Vec::new();
+        for &cell in cells.iter() {
+            for (basis, offset) in self.basis.iter().enumerate() {
+                let mut position = *offset;
+                for (vector, &n) in self.vectors.iter().zip(cell.iter()) {
+                    for (x, v) in position.iter_mut().zip(vector) {
+                        *x += n as f64 * v;
+                    }
+                }
+                nodes.push((cell, basis));
+                positions.push(position);
+                sites.push(CellSite {
+                    cell: pad(cell),
+                    basis,
+                });
+            }
+        }
+        let mut constructor = LatticeConstructor::with_positions(nodes, positions)
+            .with_cells(sites)
+            .with_boundaries(pad(boundaries));
+        for &cell in cells.iter() {
+            for bond in self.bonds.iter() {
+                let mut target = Some(cell);
+                for axis in 0..D {
+                    target =
+                        target.and_then(|t| step(t, axis, bond.offset[axis], counts, boundaries));
+                }
+                if let Some(target) = target {
+                    constructor.add_typed_edge(
+                        (cell, bond.from),
+                        (target, bond.to),
+                        bond.edge_type.clone(),
+                        1.0,
+                        pad(bond.offset.map(|n| n as i32)),
+                    );
+                }
+            }
+        }
+        Ok(constructor)
+    }
+}
+
+/// Checks that every axis has sites and fits its boundary, where periodic
+/// axes need an even length if `even_periods`.
+fn validate<T, const D: usize>(
+    lengths: [usize; D],
+    boundaries: [Boundary; D],
+    even_periods: bool,
+) -> Result<(), LatticeError<T>> {
+    for axis in 0..D {
+        let length = lengths[axis];
+        if length == 0 {
+            return Err(LatticeError::EmptyAxis(axis));
+        }
+        match boundaries[axis] {
+            Boundary::Open => {}
+            Boundary::Periodic => {
+                if even_periods && length % 2 == 1 {
+                    return Err(LatticeError::BadPeriod { axis, length });
+                }
+            }
+            Boundary::Twisted(shift) => {
+                // the shift runs along the first axis, which must close itself
+                if axis == 0
+                    || boundaries[0] != Boundary::Periodic
+                    || shift >= lengths[0]
+                    || (even_periods && (length + shift) % 2 == 1)
+                {
+                    return Err(LatticeError::BadTwist { axis, shift });
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The site `delta` steps from `site` along `axis`, `None` across an open
+/// boundary.
+fn step<const D: usize>(
+    mut site: [usize; D],
+    axis: usize,
+    delta: isize,
+    lengths: [usize; D],
+    boundaries: [Boundary; D],
+) -> Option<[usize; D]> {
+    let next = site[axis] as isize + delta;
+    let length = lengths[axis] as isize;
+    site[axis] = next.rem_euclid(length) as usize;
+    if (0..length).contains(&next) {
+        return Some(site);
+    }
+    let shift = match boundaries[axis] {
+        Boundary::Open => return None,
+        Boundary::Periodic => 0,
+        Boundary::Twisted(shift) => shift as isize * next.div_euclid(length),
+    };
+    site[0] = (site[0] as isize + shift).rem_euclid(lengths[0] as isize) as usize;
+    Some(site)
+}
+
+/// The first axes of `values`, the default along the others.
+fn pad<V: Copy + Default, const D: usize>(values: [V; D]) -> [V; 3] {
+    let mut padded = [V::default(); 3];
+    padded[..D].copy_from_slice(&values);
+    padded
+}
+
+/// Nodes `(x, y)` of a `width x height` grid and their positions.
+fn grid(width: usize, height: usize) -> (Vec<(usize, usize)>, Vec<[f64; 3]>) {
+    let nodes: Vec<(usize, usize)> = (0..width)
+        .flat_map(|x| (0..height).map(move |y| (x, y)))
+        .collect();
+    let positions = nodes
+        .iter()
+        .map(|&(x, y)| [x as f64, y as f64, 0.0])
+        .collect();
+    (nodes, positions)
+}
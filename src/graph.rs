@@ -0,0 +1,262 @@
+//! Lattices as JSON edge lists, for geometries generated by other programs.
+//!
+//! A [`Graph`] names every site by a label and lists the edges between them:
+//!
+//! ```json
+//! {
+//!   "sites": [
+//!     { "label": "a", "position": [0.0, 0.0, 0.0] },
+//!     { "label": "b", "position": [1.0, 0.0, 0.0] },
+//!     { "label": "c", "position": [2.0, 0.0, 0.0] }
+//!   ],
+//!   "classes": { "strong": 2.0 },
+//!   "edges": [
+//!     { "a": "a", "b": "b", "class": "strong" },
+//!     { "a": "b", "b": "c", "coupling": -0.5 }
+//!   ]
+//! }
+//! ```
+//!
+//! An edge takes its own `coupling` if it has one, otherwise the coupling of
+//! its bond `class` in `classes`, otherwise 1. Positions are optional but must
+//! be given for all sites or none. Edges may also carry the `displacement` of
+//! [`crate::lattice::Edge`], and the graph the `boundaries` of the lattice
+//! and its `plaquettes`.
+
+use crate::invalid;
+use crate::lattice::{Boundary, Edge, Even_Site_Id, Lattice, LatticeConstructor, Odd_Site_Id};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fmt::Display;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Which sublattice [`Graph::from_lattice`] found a site on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Sublattice {
+    Even,
+    Odd,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GraphSite {
+    pub label: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub position: Option<[f64; 3]>,
+    /// Written on export for other programs, the coloring is recomputed on
+    /// import.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sublattice: Option<Sublattice>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GraphEdge {
+    pub a: String,
+    pub b: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub coupling: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub class: Option<String>,
+    /// Lattice vector from `a` to `b`.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub displacement: [i32; 3],
+}
+
+/// The singlet projectors on the bonds `a` and `b`, see
+/// [`LatticeConstructor::add_plaquette`].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GraphPlaquette {
+    pub a: [String; 2],
+    pub b: [String; 2],
+    pub coupling: f64,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Graph {
+    pub sites: Vec<GraphSite>,
+    /// Coupling of every bond class.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub classes: BTreeMap<String, f64>,
+    pub edges: Vec<GraphEdge>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub plaquettes: Vec<GraphPlaquette>,
+    #[serde(default, skip_serializing_if = "is_open")]
+    pub boundaries: [Boundary; 3],
+}
+
+fn is_zero(displacement: &[i32; 3]) -> bool {
+    *displacement == [0; 3]
+}
+
+fn is_open(boundaries: &[Boundary; 3]) -> bool {
+    *boundaries == [Boundary::Open; 3]
+}
+
+impl Graph {
+    /// The sites and edges of `lattice`, with the even sites labelled `e0`,
+    /// `e1`, ... and the odd ones `o0`, `o1`, ...
+    pub fn from_lattice(lattice: &Lattice) -> Graph {
+        let even: Vec<String> = (0..lattice.num_even).map(|i| format!("e{}", i)).collect();
+        let odd: Vec<String> = (0..lattice.num_odd).map(|i| format!("o{}", i)).collect();
+        Graph::labelled(lattice, &even, &odd)
+    }
+
+    /// [`Graph::from_lattice`] with the labels of the `even` and `odd` sites,
+    /// as returned by [`LatticeConstructor::get_bipartite_coloring`] for the
+    /// constructor of `lattice`.
+    ///
+    /// The even sites come first, so importing the graph again numbers the
+    /// sites and edges as in `lattice`. Fails with
+    /// [`io::ErrorKind::InvalidInput`] unless every site has one label.
+    pub fn from_labelled_lattice<T: Display>(
+        lattice: &Lattice,
+        even: &[T],
+        odd: &[T],
+    ) -> io::Result<Graph> {
+        if (even.len(), odd.len()) != (lattice.num_even, lattice.num_odd) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "{} even and {} odd labels for {} even and {} odd sites",
+                    even.len(),
+                    odd.len(),
+                    lattice.num_even,
+                    lattice.num_odd
+                ),
+            ));
+        }
+        Ok(Graph::labelled(lattice, even, odd))
+    }
+
+    /// [`Graph::from_labelled_lattice`] with one label for every site.
+    fn labelled<T: Display>(lattice: &Lattice, even: &[T], odd: &[T]) -> Graph {
+        let mut sites = Vec::with_capacity(even.len() + odd.len());
+        for (sublattice, labels) in [(Sublattice::Even, even), (Sublattice::Odd, odd)] {
+            for (i, label) in labels.iter().enumerate() {
+                let position = lattice.positions.as_ref().map(|p| match sublattice {
+                    Sublattice::Even => p.even[Even_Site_Id(i)],
+                    Sublattice::Odd => p.odd[Odd_Site_Id(i)],
+                });
+                sites.push(GraphSite {
+                    label: label.to_string(),
+                    position,
+                    sublattice: Some(sublattice),
+                });
+            }
+        }
+        let ends = |edge: &Edge| {
+            [
+                sites[edge.even.0].label.clone(),
+                sites[lattice.num_even + edge.odd.0].label.clone(),
+            ]
+        };
+        let edges = lattice
+            .edges
+            .iter()
+            .zip(lattice.edge_types.iter())
+            .map(|(edge, edge_type)| {
+                let [a, b] = ends(edge);
+                GraphEdge {
+                    a,
+                    b,
+                    coupling: Some(edge.coupling),
+                    class: edge_type.as_ref().map(|t| t.0.clone()),
+                    displacement: edge.displacement,
+                }
+            })
+            .collect();
+        let plaquettes = lattice
+            .plaquettes
+            .iter()
+            .map(|plaquette| GraphPlaquette {
+                a: ends(&plaquette.edges[0]),
+                b: ends(&plaquette.edges[1]),
+                coupling: plaquette.coupling,
+            })
+            .collect();
+        Graph {
+            sites,
+            classes: BTreeMap::new(),
+            edges,
+            plaquettes,
+            boundaries: lattice.boundaries,
+        }
+    }
+
+    /// The coupling of `edge`: its own, else the one of its class, else 1.
+    pub fn coupling(&self, edge: &GraphEdge) -> f64 {
+        edge.coupling
+            .or_else(|| {
+                edge.class
+                    .as_ref()
+                    .and_then(|c| self.classes.get(c).copied())
+            })
+            .unwrap_or(1.0)
+    }
+
+    /// Checks that the labels are unique and that all sites or none have a
+    /// position. Everything else is checked by [`LatticeConstructor::build`].
+    pub fn validate(&self) -> io::Result<()> {
+        let mut labels = HashSet::with_capacity(self.sites.len());
+        for site in self.sites.iter() {
+            if !labels.insert(site.label.as_str()) {
+                return Err(invalid(format!("site {} appears twice", site.label)));
+            }
+        }
+        let placed = self.sites.iter().filter(|s| s.position.is_some()).count();
+        if placed != 0 && placed != self.sites.len() {
+            return Err(invalid(format!(
+                "{} of {} sites have a position",
+                placed,
+                self.sites.len()
+            )));
+        }
+        Ok(())
+    }
+
+    /// The constructor of the lattice, with nodes named by the labels of the
+    /// sites.
+    pub fn constructor(&self) -> io::Result<LatticeConstructor<String>> {
+        self.validate()?;
+        let nodes: Vec<String> = self.sites.iter().map(|s| s.label.clone()).collect();
+        let positions: Option<Vec<[f64; 3]>> = self.sites.iter().map(|s| s.position).collect();
+        let mut constructor = match positions {
+            Some(positions) => LatticeConstructor::with_positions(nodes, positions),
+            None => LatticeConstructor::new(nodes),
+        }
+        .with_boundaries(self.boundaries);
+        for edge in self.edges.iter() {
+            let coupling = self.coupling(edge);
+            let (a, b) = (edge.a.clone(), edge.b.clone());
+            match edge.class.as_ref() {
+                Some(class) => {
+                    constructor.add_typed_edge(a, b, class.as_str(), coupling, edge.displacement)
+                }
+                None => constructor.add_displaced_edge(a, b, coupling, edge.displacement),
+            }
+        }
+        for plaquette in self.plaquettes.iter() {
+            let [a0, a1] = plaquette.a.clone();
+            let [b0, b1] = plaquette.b.clone();
+            constructor.add_plaquette((a0, a1), (b0, b1), plaquette.coupling);
+        }
+        Ok(constructor)
+    }
+
+    /// Reads a graph written as JSON and validates it.
+    pub fn load(path: &Path) -> io::Result<Graph> {
+        let graph: Graph = serde_json::from_slice(&fs::read(path)?)?;
+        graph.validate()?;
+        Ok(graph)
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        fs::write(path, serde_json::to_string_pretty(self)?)
+    }
+}
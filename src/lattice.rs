@@ -1,8 +1,12 @@
+use crate::geometry::{square_dimers, DimerPattern, Geometry};
 use id_collections::{id_type, IdVec};
-use rand::distributions::Uniform;
-use rand::{rngs::ThreadRng, Rng};
-use std::fmt::Debug;
-use std::{mem::swap, vec};
+use rand::distributions::WeightedIndex;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::error::Error;
+use std::fmt::{self, Debug};
+use std::hash::Hash;
 
 pub fn new_rectangle(width: usize, height: usize) -> LatticeConstructor<(usize, usize)> {
     let mut nodes = Vec::new();
@@ -14,105 +18,570 @@ pub fn new_rectangle(width: usize, height: usize) -> LatticeConstructor<(usize,
     LatticeConstructor::new(nodes)
 }
 
-#[derive(PartialEq, Eq, Clone, Copy, Debug)]
-pub enum EdgeType {
-    One,
-    Two,
+fn columnar_dimers(width: usize, height: usize, j1: f64) -> Geometry<(usize, usize)> {
+    Ok(square_dimers(
+        width,
+        height,
+        DimerPattern::Columnar,
+        [Boundary::Periodic; 2],
+    )?
+    .coupling("dimer", j1))
+}
+
+/// The `width x height` torus with columnar dimers: every other row of
+/// vertical bonds has coupling `j1`, all other bonds have coupling 1.
+pub fn make_latice(
+    width: usize,
+    height: usize,
+    j1: f64,
+) -> Result<Lattice, LatticeError<(usize, usize)>> {
+    columnar_dimers(width, height, j1)?.build()
+}
+
+/// [`make_latice`] with the plaquette term `q` on both pairs of parallel
+/// bonds of every square, the J-Q model for `j1 = 1`.
+pub fn make_jq_latice(
+    width: usize,
+    height: usize,
+    j1: f64,
+    q: f64,
+) -> Result<Lattice, LatticeError<(usize, usize)>> {
+    let mut constructor = columnar_dimers(width, height, j1)?;
+    for x in 0..width {
+        for y in 0..height {
+            let (x1, y1) = ((x + 1) % width, (y + 1) % height);
+            constructor.add_plaquette(((x, y), (x1, y)), ((x, y1), (x1, y1)), q);
+            constructor.add_plaquette(((x, y), (x, y1)), ((x1, y), (x1, y1)), q);
+        }
+    }
+    constructor.build()
+}
+
+/// An odd cycle of nodes, which rules out the even and odd sublattices.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NotBipartite<T> {
+    /// Nodes around the cycle, each joined by an edge to the next and the
+    /// last to the first.
+    pub cycle: Vec<T>,
+}
+impl<T: Debug> fmt::Display for NotBipartite<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Graph is not bipartite, odd cycle {:?}", self.cycle)
+    }
+}
+impl<T: Debug> Error for NotBipartite<T> {}
+
+/// Why [`LatticeConstructor::build`] rejected a graph.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LatticeError<T> {
+    /// An edge ends on a node that is not in the graph.
+    UnknownNode(T),
+    /// An edge joins a node to itself.
+    SelfLoop(T),
+    /// Two edges join the same nodes with the same displacement.
+    DuplicateEdge(T, T),
+    /// The edges close a cycle of an odd number of nodes, held in order.
+    NotBipartite(NotBipartite<T>),
+    /// A node without edges, which no operator would ever act on.
+    IsolatedSite(T),
+    /// No nodes, or no edge with a nonzero coupling.
+    Empty,
+    /// A bond of a plaquette that was not added as an edge.
+    NotAnEdge(T, T),
+    /// Both bonds of a plaquette end on this node.
+    SharedPlaquetteSite(T),
+    /// A plaquette on the bond between these nodes with a negative coupling.
+    NegativePlaquette(T, T),
+    /// An edge between these nodes whose coupling is not finite.
+    NonFiniteCoupling(T, T),
+    /// An edge or plaquette of a [`Lattice`] whose id is not its position.
+    MisplacedId(usize),
+    /// A number of positions other than the number of nodes.
+    PositionCount { nodes: usize, positions: usize },
+    /// A number of cells other than the number of nodes.
+    CellCount { nodes: usize, cells: usize },
+    /// [`LatticeConstructor::coupling`] of a type no edge has.
+    UnknownEdgeType(EdgeType),
+    /// An axis of a geometry without sites.
+    EmptyAxis(usize),
+    /// A periodic axis whose length makes the lattice non-bipartite or
+    /// wraps a site onto itself.
+    BadPeriod { axis: usize, length: usize },
+    /// A twist along the first axis, or one that makes the lattice
+    /// non-bipartite.
+    BadTwist { axis: usize, shift: usize },
+}
+impl<T: Debug> fmt::Display for LatticeError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LatticeError::UnknownNode(node) => write!(f, "Node {:?} not in graph", node),
+            LatticeError::SelfLoop(node) => write!(f, "Edge from {:?} to itself", node),
+            LatticeError::DuplicateEdge(a, b) => {
+                write!(f, "Duplicate edge between {:?} and {:?}", a, b)
+            }
+            LatticeError::NotBipartite(error) => write!(f, "{}", error),
+            LatticeError::IsolatedSite(node) => write!(f, "Node {:?} has no edges", node),
+            LatticeError::Empty => write!(f, "Lattice has no edges with a nonzero coupling"),
+            LatticeError::NotAnEdge(a, b) => {
+                write!(f, "Plaquette bond {:?} - {:?} is not an edge", a, b)
+            }
+            LatticeError::SharedPlaquetteSite(node) => {
+                write!(f, "Both bonds of a plaquette end on {:?}", node)
+            }
+            LatticeError::NegativePlaquette(a, b) => {
+                write!(f, "Plaquette on {:?} - {:?} has a negative coupling", a, b)
+            }
+            LatticeError::NonFiniteCoupling(a, b) => {
+                write!(f, "Edge between {:?} and {:?} has no finite coupling", a, b)
+            }
+            LatticeError::MisplacedId(id) => write!(f, "Id {} is not its position", id),
+            LatticeError::PositionCount { nodes, positions } => {
+                write!(f, "{} positions for {} nodes", positions, nodes)
+            }
+            LatticeError::CellCount { nodes, cells } => {
+                write!(f, "{} cells for {} nodes", cells, nodes)
+            }
+            LatticeError::UnknownEdgeType(edge_type) => {
+                write!(f, "No edge of type {}", edge_type)
+            }
+            LatticeError::EmptyAxis(axis) => write!(f, "Axis {} has no sites", axis),
+            LatticeError::BadPeriod { axis, length } => {
+                write!(f, "Axis {} cannot be periodic with length {}", axis, length)
+            }
+            LatticeError::BadTwist { axis, shift } => {
+                write!(f, "Axis {} cannot be twisted by {}", axis, shift)
+            }
+        }
+    }
+}
+impl<T: Debug> Error for LatticeError<T> {}
+
+/// Splits `num_sites` sites into two classes so that bond `(i, j, flip)` joins
+/// sites of different classes exactly when `flip`, returning `true` for the
+/// class of every site. A breadth-first search puts the first site of every
+/// component into class `false`.
+///
+/// If there is no such split, returns a cycle that contradicts it as its
+/// sites and the indices of the bonds joining each site to the next, the last
+/// bond closing the cycle.
+pub(crate) fn two_coloring(
+    num_sites: usize,
+    bonds: &[(usize, usize, bool)],
+) -> Result<Vec<bool>, (Vec<usize>, Vec<usize>)> {
+    // the bonds at site `i` are `incident[start[i]..start[i + 1]]`
+    let mut start = vec![0; num_sites + 1];
+    for &(i, j, _) in bonds.iter() {
+        start[i + 1] += 1;
+        start[j + 1] += 1;
+    }
+    for i in 0..num_sites {
+        start[i + 1] += start[i];
+    }
+    let mut incident = vec![0; start[num_sites]];
+    let mut next = start.clone();
+    for (bond, &(i, j, _)) in bonds.iter().enumerate() {
+        for site in [i, j] {
+            incident[next[site]] = bond;
+            next[site] += 1;
+        }
+    }
+    let mut class: Vec<Option<bool>> = vec![None; num_sites];
+    // the site and bond every site was reached from
+    let mut parent: Vec<Option<(usize, usize)>> = vec![None; num_sites];
+    for root in 0..num_sites {
+        if class[root].is_some() {
+            continue;
+        }
+        class[root] = Some(false);
+        let mut queue = VecDeque::from([root]);
+        while let Some(i) = queue.pop_front() {
+            let class_i = class[i].unwrap();
+            for &bond in incident[start[i]..start[i + 1]].iter() {
+                let (a, b, flip) = bonds[bond];
+                let j = if a == i { b } else { a };
+                match class[j] {
+                    None => {
+                        class[j] = Some(class_i != flip);
+                        parent[j] = Some((i, bond));
+                        queue.push_back(j);
+                    }
+                    Some(class_j) if (class_i != class_j) != flip => {
+                        let path = |mut site: usize| {
+                            let (mut sites, mut bonds) = (vec![site], Vec::new());
+                            while let Some((up, bond)) = parent[site] {
+                                sites.push(up);
+                                bonds.push(bond);
+                                site = up;
+                            }
+                            (sites, bonds)
+                        };
+                        let (mut sites, mut cycle_bonds) = path(i);
+                        let (mut other_sites, mut other_bonds) = path(j);
+                        // both paths end at the root, keep them up to where they meet
+                        while sites.len() > 1
+                            && other_sites.len() > 1
+                            && sites[sites.len() - 2] == other_sites[other_sites.len() - 2]
+                        {
+                            sites.pop();
+                            other_sites.pop();
+                            cycle_bonds.pop();
+                            other_bonds.pop();
+                        }
+                        other_sites.pop();
+                        sites.extend(other_sites.into_iter().rev());
+                        cycle_bonds.extend(other_bonds.into_iter().rev());
+                        cycle_bonds.push(bond);
+                        return Err((sites, cycle_bonds));
+                    }
+                    Some(_) => {}
+                }
+            }
+        }
+    }
+    Ok(class.into_iter().map(|c| c.unwrap()).collect())
+}
+
+/// How a lattice closes along one axis.
+///
+/// A cylinder is periodic along one axis and open along the other.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Boundary {
+    #[default]
+    Open,
+    Periodic,
+    /// Periodic, where crossing the boundary also moves `shift` sites along
+    /// the first axis.
+    Twisted(usize),
+}
+
+/// Unit cell and basis site of a site of a lattice built from a
+/// [`crate::geometry::UnitCell`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct CellSite {
+    /// Number of lattice vectors to the cell along every axis.
+    pub cell: [usize; 3],
+    /// Position of the site in the basis of the cell.
+    pub basis: usize,
+}
+
+/// Label of a class of equivalent bonds, such as the legs and rungs of a
+/// ladder, whose coupling is set with [`LatticeConstructor::coupling`].
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct EdgeType(pub String);
+impl From<&str> for EdgeType {
+    fn from(name: &str) -> EdgeType {
+        EdgeType(name.to_string())
+    }
+}
+impl fmt::Display for EdgeType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
 }
 
 struct EdgeConstructor<T: Eq> {
     pub a: T,
     pub b: T,
-    pub edge_type: EdgeType,
+    pub coupling: f64,
+    pub displacement: [i32; 3],
+    pub edge_type: Option<EdgeType>,
 }
-pub struct LatticeConstructor<T: Eq + Clone> {
+struct PlaquetteConstructor<T: Eq> {
+    pub a: (T, T),
+    pub b: (T, T),
+    pub coupling: f64,
+}
+pub struct LatticeConstructor<T: Eq + Hash + Clone> {
     nodes: Vec<T>,
+    positions: Option<Vec<[f64; 3]>>,
+    cells: Option<Vec<CellSite>>,
+    boundaries: [Boundary; 3],
     edges: Vec<EdgeConstructor<T>>,
+    plaquettes: Vec<PlaquetteConstructor<T>>,
+    /// The first type given to [`LatticeConstructor::coupling`] without edges.
+    unknown_edge_type: Option<EdgeType>,
 }
 
-impl<T: Eq + Clone> LatticeConstructor<T> {
+impl<T: Eq + Hash + Clone> LatticeConstructor<T> {
     pub fn new(nodes: Vec<T>) -> LatticeConstructor<T> {
         LatticeConstructor {
-            nodes: nodes,
+            nodes,
+            positions: None,
+            cells: None,
+            boundaries: [Boundary::Open; 3],
             edges: Vec::new(),
+            plaquettes: Vec::new(),
+            unknown_edge_type: None,
+        }
+    }
+    /// Records how the displacements of the edges close along every axis,
+    /// see [`Lattice::boundaries`].
+    pub fn with_boundaries(mut self, boundaries: [Boundary; 3]) -> LatticeConstructor<T> {
+        self.boundaries = boundaries;
+        self
+    }
+    /// Records the unit cell of every node, which [`Lattice::cells`] keeps.
+    pub fn with_cells(mut self, cells: Vec<CellSite>) -> LatticeConstructor<T> {
+        self.cells = Some(cells);
+        self
+    }
+    /// Nodes placed at the real-space `positions`, which [`Lattice::positions`]
+    /// keeps.
+    pub fn with_positions(nodes: Vec<T>, positions: Vec<[f64; 3]>) -> LatticeConstructor<T> {
+        LatticeConstructor {
+            positions: Some(positions),
+            ..LatticeConstructor::new(nodes)
         }
     }
-    pub fn add_edge(&mut self, a: T, b: T, edge_type: EdgeType) {
-        self.edges.push(EdgeConstructor { a, b, edge_type });
+    /// Adds the bond `coupling * S_a . S_b` to the hamiltonian, without a
+    /// constant shift and with the anisotropy of [`crate::model::Model`],
+    /// ferromagnetic for a negative `coupling`.
+    pub fn add_edge(&mut self, a: T, b: T, coupling: f64) {
+        self.add_displaced_edge(a, b, coupling, [0; 3]);
     }
-    pub fn get_bipartite_coloring(&self) -> (Vec<T>, Vec<T>) {
+    /// [`LatticeConstructor::add_edge`] where `b` lies `displacement` lattice
+    /// vectors away from `a`, which winding numbers are measured with.
+    pub fn add_displaced_edge(&mut self, a: T, b: T, coupling: f64, displacement: [i32; 3]) {
+        self.edges.push(EdgeConstructor {
+            a,
+            b,
+            coupling,
+            displacement,
+            edge_type: None,
+        });
+    }
+    /// [`LatticeConstructor::add_displaced_edge`] for a bond of type
+    /// `edge_type`.
+    pub fn add_typed_edge(
+        &mut self,
+        a: T,
+        b: T,
+        edge_type: impl Into<EdgeType>,
+        coupling: f64,
+        displacement: [i32; 3],
+    ) {
+        self.add_displaced_edge(a, b, coupling, displacement);
+        self.edges.last_mut().unwrap().edge_type = Some(edge_type.into());
+    }
+    /// Sets the coupling of every edge of type `edge_type`. A type without
+    /// edges makes [`LatticeConstructor::build`] fail with
+    /// [`LatticeError::UnknownEdgeType`].
+    pub fn coupling(mut self, edge_type: &str, coupling: f64) -> LatticeConstructor<T> {
+        let mut found = false;
+        for edge in self.edges.iter_mut() {
+            if edge.edge_type.as_ref().is_some_and(|t| t.0 == edge_type) {
+                edge.coupling = coupling;
+                found = true;
+            }
+        }
+        if !found && self.unknown_edge_type.is_none() {
+            self.unknown_edge_type = Some(edge_type.into());
+        }
+        self
+    }
+    /// The types of the edges in the order they first appear.
+    pub fn edge_types(&self) -> Vec<EdgeType> {
+        let mut types: Vec<EdgeType> = Vec::new();
+        for edge_type in self.edges.iter().filter_map(|e| e.edge_type.as_ref()) {
+            if !types.contains(edge_type) {
+                types.push(edge_type.clone());
+            }
+        }
+        types
+    }
+    /// Adds `-coupling P_a P_b` to the hamiltonian, where `P = 1/4 - S_i . S_j`
+    /// is the singlet projector on a bond. Both bonds must already be edges.
+    pub fn add_plaquette(&mut self, a: (T, T), b: (T, T), coupling: f64) {
+        self.plaquettes
+            .push(PlaquetteConstructor { a, b, coupling });
+    }
+    /// Position in `nodes` of every node, the first one for repeated nodes.
+    fn index(&self) -> HashMap<&T, usize> {
+        let mut index = HashMap::with_capacity(self.nodes.len());
+        for (i, node) in self.nodes.iter().enumerate() {
+            index.entry(node).or_insert(i);
+        }
+        index
+    }
+    /// Positions in `nodes` of both ends of every edge.
+    fn ends(&self, index: &HashMap<&T, usize>) -> Result<Vec<(usize, usize)>, LatticeError<T>> {
+        let position = |node: &T| {
+            index
+                .get(node)
+                .copied()
+                .ok_or_else(|| LatticeError::UnknownNode(node.clone()))
+        };
+        let mut ends = Vec::with_capacity(self.edges.len());
         for edge in self.edges.iter() {
-            if !self.nodes.contains(&edge.a) {
-                panic!("Node not in graph");
+            let (a, b) = (position(&edge.a)?, position(&edge.b)?);
+            if a == b {
+                return Err(LatticeError::SelfLoop(edge.a.clone()));
             }
-            if !self.nodes.contains(&edge.b) {
-                panic!("Node not in graph");
+            ends.push((a, b));
+        }
+        Ok(ends)
+    }
+    /// Whether every node is odd, given the `ends` of the edges.
+    fn coloring(&self, ends: &[(usize, usize)]) -> Result<Vec<bool>, LatticeError<T>> {
+        let bonds: Vec<(usize, usize, bool)> = ends.iter().map(|&(a, b)| (a, b, true)).collect();
+        two_coloring(self.nodes.len(), &bonds).map_err(|(cycle, _)| {
+            LatticeError::NotBipartite(NotBipartite {
+                cycle: cycle.into_iter().map(|i| self.nodes[i].clone()).collect(),
+            })
+        })
+    }
+    /// Splits the nodes into the even and the odd sublattice, each in the order
+    /// of `nodes`, with the first node of every connected component even.
+    pub fn get_bipartite_coloring(&self) -> Result<(Vec<T>, Vec<T>), LatticeError<T>> {
+        let odd_nodes = self.coloring(&self.ends(&self.index())?)?;
+        let (mut even, mut odd) = (Vec::new(), Vec::new());
+        for (node, odd_node) in self.nodes.iter().zip(odd_nodes) {
+            if odd_node {
+                odd.push(node.clone());
+            } else {
+                even.push(node.clone());
             }
         }
-        let mut even = Vec::new();
-        let mut odd = Vec::new();
-        even.push(self.nodes[0].clone());
-        while even.len() + odd.len() < self.nodes.len() {
-            let mut progress = false;
-            for edge in self.edges.iter() {
-                match (
-                    even.contains(&edge.a),
-                    odd.contains(&edge.a),
-                    even.contains(&edge.b),
-                    odd.contains(&edge.b),
-                ) {
-                    (true, _, true, _) | (_, true, _, true) => panic!("Graph is not bipartite"),
-                    (true, false, false, false) => {
-                        odd.push(edge.b.clone());
-                        progress = true;
-                    }
-                    (false, true, false, false) => {
-                        even.push(edge.b.clone());
-                        progress = true;
-                    }
-                    (false, false, true, false) => {
-                        odd.push(edge.a.clone());
-                        progress = true;
-                    }
-                    (false, false, false, true) => {
-                        even.push(edge.a.clone());
-                        progress = true;
-                    }
-                    (true, false, false, true) | (false, true, true, false) => {}
-                    _ => panic!("Error in bipartite coloring"),
+        Ok((even, odd))
+    }
+    /// Colors the graph and numbers the sites of either sublattice in the
+    /// order of `nodes`, see [`LatticeConstructor::get_bipartite_coloring`].
+    pub fn build(self) -> Result<Lattice, LatticeError<T>> {
+        if let Some(edge_type) = self.unknown_edge_type {
+            return Err(LatticeError::UnknownEdgeType(edge_type));
+        }
+        if self.nodes.is_empty() {
+            return Err(LatticeError::Empty);
+        }
+        let nodes = self.nodes.len();
+        if let Some(positions) = self.positions.as_ref().map(Vec::len) {
+            if positions != nodes {
+                return Err(LatticeError::PositionCount { nodes, positions });
+            }
+        }
+        if let Some(cells) = self.cells.as_ref().map(Vec::len) {
+            if cells != nodes {
+                return Err(LatticeError::CellCount { nodes, cells });
+            }
+        }
+        if let Some(edge) = self.edges.iter().find(|e| !e.coupling.is_finite()) {
+            return Err(LatticeError::NonFiniteCoupling(
+                edge.a.clone(),
+                edge.b.clone(),
+            ));
+        }
+        let index = self.index();
+        let ends = self.ends(&index)?;
+        let odd_nodes = self.coloring(&ends)?;
+        // id of every node within its sublattice
+        let (mut num_even, mut num_odd) = (0, 0);
+        let ids: Vec<usize> = odd_nodes
+            .iter()
+            .map(|&odd| {
+                let count = if odd { &mut num_odd } else { &mut num_even };
+                *count += 1;
+                *count - 1
+            })
+            .collect();
+        let mut edges = Vec::with_capacity(self.edges.len());
+        for (edge, &(a, b)) in self.edges.iter().zip(ends.iter()) {
+            let (even, odd, displacement) = if odd_nodes[a] {
+                (b, a, edge.displacement.map(|d| -d))
+            } else {
+                (a, b, edge.displacement)
+            };
+            edges.push(Edge {
+                id: edges.len(),
+                coupling: edge.coupling,
+                displacement,
+                even: Even_Site_Id(ids[even]),
+                odd: Odd_Site_Id(ids[odd]),
+            });
+        }
+        // parallel edges are only distinct if they wind differently
+        let mut at_even = vec![Vec::new(); num_even];
+        for edge in edges.iter() {
+            at_even[edge.even.0].push((edge.odd.0, edge.displacement, edge.id));
+        }
+        for mut keys in at_even {
+            keys.sort_unstable();
+            for pair in keys.windows(2) {
+                if (pair[0].0, pair[0].1) == (pair[1].0, pair[1].1) {
+                    let edge = &self.edges[pair[1].2];
+                    return Err(LatticeError::DuplicateEdge(edge.a.clone(), edge.b.clone()));
                 }
             }
-            if !progress {
-                for node in self.nodes.iter() {
-                    if !even.contains(node) && !odd.contains(node) {
-                        even.push(node.clone());
-                        break;
-                    }
+        }
+        let mut degree = vec![0; self.nodes.len()];
+        for &(a, b) in ends.iter() {
+            degree[a] += 1;
+            degree[b] += 1;
+        }
+        if let Some(i) = degree.iter().position(|&d| d == 0) {
+            return Err(LatticeError::IsolatedSite(self.nodes[i].clone()));
+        }
+        if edges.iter().all(|e| e.coupling == 0.0) {
+            return Err(LatticeError::Empty);
+        }
+        // the first edge between every pair of nodes
+        let mut edge_of = HashMap::new();
+        if !self.plaquettes.is_empty() {
+            for (position, &(a, b)) in ends.iter().enumerate() {
+                edge_of.entry((a.min(b), a.max(b))).or_insert(position);
+            }
+        }
+        let mut plaquettes = Vec::with_capacity(self.plaquettes.len());
+        for plaquette in self.plaquettes.iter() {
+            if plaquette.coupling.is_nan() || plaquette.coupling < 0.0 {
+                let (a, b) = plaquette.a.clone();
+                return Err(LatticeError::NegativePlaquette(a, b));
+            }
+            let edge = |(a, b): &(T, T)| {
+                let (i, j) = match (index.get(a), index.get(b)) {
+                    (Some(&i), Some(&j)) => (i, j),
+                    (None, _) => return Err(LatticeError::UnknownNode(a.clone())),
+                    (_, None) => return Err(LatticeError::UnknownNode(b.clone())),
+                };
+                match edge_of.get(&(i.min(j), i.max(j))) {
+                    Some(&position) => Ok((edges[position], [i, j])),
+                    None => Err(LatticeError::NotAnEdge(a.clone(), b.clone())),
                 }
+            };
+            let (a, ends_a) = edge(&plaquette.a)?;
+            let (b, ends_b) = edge(&plaquette.b)?;
+            if let Some(&shared) = ends_a.iter().find(|i| ends_b.contains(i)) {
+                return Err(LatticeError::SharedPlaquetteSite(
+                    self.nodes[shared].clone(),
+                ));
             }
+            plaquettes.push(Plaquette {
+                id: plaquettes.len(),
+                coupling: plaquette.coupling,
+                edges: [a, b],
+            });
         }
-        (even, odd)
+        let mut lattice = Lattice::unchecked(num_even, num_odd, edges);
+        lattice.plaquettes = plaquettes;
+        lattice.edge_types = self.edges.into_iter().map(|e| e.edge_type).collect();
+        lattice.boundaries = self.boundaries;
+        lattice.positions = self.positions.map(|p| by_sublattice(p, &odd_nodes));
+        lattice.cells = self.cells.map(|c| by_sublattice(c, &odd_nodes));
+        Ok(lattice)
     }
-    pub fn build(self) -> Lattice {
-        let (even, odd) = self.get_bipartite_coloring();
-        let mut edges = Vec::new();
-        for edge in self.edges.iter() {
-            let even_id = even
-                .iter()
-                .position(|x| x == &edge.a || x == &edge.b)
-                .unwrap();
-            let odd_id = odd
-                .iter()
-                .position(|x| x == &edge.a || x == &edge.b)
-                .unwrap();
-            edges.push(Edge {
-                edge_type: edge.edge_type,
-                even: Even_Site_Id(even_id),
-                odd: Odd_Site_Id(odd_id),
-            });
+}
+
+/// The `values` of the nodes, split like the nodes into the sublattices.
+fn by_sublattice<V>(values: Vec<V>, odd_nodes: &[bool]) -> Bipartite_Id_Vec<V> {
+    let mut sites = Bipartite_Id_Vec::new();
+    for (value, &odd) in values.into_iter().zip(odd_nodes.iter()) {
+        if odd {
+            let _ = sites.odd.push(value);
+        } else {
+            let _ = sites.even.push(value);
         }
-        Lattice::new(even.len(), odd.len(), edges)
     }
+    sites
 }
 
 #[id_type]
@@ -130,6 +599,11 @@ pub struct Bipartite_Id_Vec<T> {
     pub even: IdVec<Even_Site_Id, T>,
     pub odd: IdVec<Odd_Site_Id, T>,
 }
+impl<T> Default for Bipartite_Id_Vec<T> {
+    fn default() -> Bipartite_Id_Vec<T> {
+        Bipartite_Id_Vec::new()
+    }
+}
 impl<T> Bipartite_Id_Vec<T> {
     pub fn new() -> Bipartite_Id_Vec<T> {
         Bipartite_Id_Vec {
@@ -159,39 +633,148 @@ impl<T> Bipartite_Id_Vec<T> {
 
 #[derive(Clone, Copy, Debug)]
 pub struct Edge {
-    pub edge_type: EdgeType,
+    /// Position in `Lattice::edges`, distinguishes parallel edges.
+    pub id: usize,
+    pub coupling: f64,
+    /// Lattice vector from the even to the odd site, zero without a geometry.
+    pub displacement: [i32; 3],
     pub even: Even_Site_Id,
     pub odd: Odd_Site_Id,
 }
 
+/// Two edges without a common site acted on by the product of their singlet
+/// projectors.
+#[derive(Clone, Copy, Debug)]
+pub struct Plaquette {
+    /// Position in `Lattice::plaquettes`.
+    pub id: usize,
+    pub coupling: f64,
+    pub edges: [Edge; 2],
+}
+
 #[derive(Clone, Debug)]
 pub struct Lattice {
     pub num_even: usize,
     pub num_odd: usize,
-    pub edge_count_1: usize,
-    pub edge_count_2: usize,
+    /// Sum of the magnitudes of the couplings of all edges.
+    pub total_coupling: f64,
     pub edges: Vec<Edge>,
-    distribution: Uniform<usize>,
+    pub plaquettes: Vec<Plaquette>,
+    /// Type of every edge, `None` for edges added without one.
+    pub edge_types: Vec<Option<EdgeType>>,
+    /// Real-space position of every site, if the lattice was built with them.
+    pub positions: Option<Bipartite_Id_Vec<[f64; 3]>>,
+    /// Unit cell and basis site of every site, if the lattice was built from
+    /// a [`crate::geometry::UnitCell`].
+    pub cells: Option<Bipartite_Id_Vec<CellSite>>,
+    /// How the lattice closes along the axes of the edge displacements, open
+    /// unless the lattice was built with them.
+    pub boundaries: [Boundary; 3],
+    distribution: WeightedIndex<f64>,
 }
 impl Lattice {
-    pub fn new(num_even: usize, num_odd: usize, edges: Vec<Edge>) -> Lattice {
-        let distribution = Uniform::new(0, edges.len());
-        let edge_count_1 = edges
-            .iter()
-            .filter(|e| e.edge_type == EdgeType::One)
-            .count();
-        let edge_count_2 = edges.len() - edge_count_1;
+    /// The lattice of `edges` between `num_even` even and `num_odd` odd
+    /// sites, which must be numbered by their positions.
+    pub fn new(
+        num_even: usize,
+        num_odd: usize,
+        edges: Vec<Edge>,
+    ) -> Result<Lattice, LatticeError<Site_Id>> {
+        for (i, edge) in edges.iter().enumerate() {
+            let (even, odd) = (Site_Id::Even(edge.even), Site_Id::Odd(edge.odd));
+            if edge.id != i {
+                return Err(LatticeError::MisplacedId(edge.id));
+            }
+            if edge.even.0 >= num_even {
+                return Err(LatticeError::UnknownNode(even));
+            }
+            if edge.odd.0 >= num_odd {
+                return Err(LatticeError::UnknownNode(odd));
+            }
+            if !edge.coupling.is_finite() {
+                return Err(LatticeError::NonFiniteCoupling(even, odd));
+            }
+        }
+        if edges.iter().all(|e| e.coupling == 0.0) {
+            return Err(LatticeError::Empty);
+        }
+        Ok(Lattice::unchecked(num_even, num_odd, edges))
+    }
+    /// [`Lattice::new`] for edges that are known to be valid.
+    fn unchecked(num_even: usize, num_odd: usize, edges: Vec<Edge>) -> Lattice {
+        let distribution = WeightedIndex::new(edges.iter().map(|e| e.coupling.abs())).unwrap();
+        let total_coupling = edges.iter().map(|e| e.coupling.abs()).sum();
         Lattice {
-            num_even: num_even,
-            num_odd: num_odd,
-            edges: edges,
+            num_even,
+            num_odd,
+            total_coupling,
+            edge_types: vec![None; edges.len()],
+            edges,
+            plaquettes: Vec::new(),
+            positions: None,
+            cells: None,
+            boundaries: [Boundary::Open; 3],
             distribution,
-            edge_count_1,
-            edge_count_2,
         }
     }
-    pub fn random_edge(&self, rng: &mut ThreadRng) -> Edge {
-        self.edges[rng.sample(self.distribution)].clone()
+    /// Adds `plaquettes`, numbered by their positions, whose bonds must not
+    /// share a site.
+    pub fn with_plaquettes(
+        mut self,
+        plaquettes: Vec<Plaquette>,
+    ) -> Result<Lattice, LatticeError<Site_Id>> {
+        for (i, plaquette) in plaquettes.iter().enumerate() {
+            let [a, b] = plaquette.edges;
+            if plaquette.id != i {
+                return Err(LatticeError::MisplacedId(plaquette.id));
+            }
+            if plaquette.coupling.is_nan() || plaquette.coupling < 0.0 {
+                return Err(LatticeError::NegativePlaquette(
+                    Site_Id::Even(a.even),
+                    Site_Id::Odd(a.odd),
+                ));
+            }
+            if a.even == b.even {
+                return Err(LatticeError::SharedPlaquetteSite(Site_Id::Even(a.even)));
+            }
+            if a.odd == b.odd {
+                return Err(LatticeError::SharedPlaquetteSite(Site_Id::Odd(a.odd)));
+            }
+        }
+        self.plaquettes = plaquettes;
+        Ok(self)
+    }
+    /// Whether worldlines can wind around `axis`.
+    pub fn wraps(&self, axis: usize) -> bool {
+        self.boundaries[axis] != Boundary::Open
+    }
+    /// This lattice with every coupling multiplied by `factor`.
+    pub fn scaled(&self, factor: f64) -> Result<Lattice, LatticeError<Site_Id>> {
+        let scale = |edge: Edge| Edge {
+            coupling: edge.coupling * factor,
+            ..edge
+        };
+        let edges = self.edges.iter().copied().map(scale).collect();
+        let plaquettes = self
+            .plaquettes
+            .iter()
+            .map(|p| Plaquette {
+                coupling: p.coupling * factor,
+                edges: p.edges.map(scale),
+                ..*p
+            })
+            .collect();
+        let mut lattice =
+            Lattice::new(self.num_even, self.num_odd, edges)?.with_plaquettes(plaquettes)?;
+        lattice.edge_types = self.edge_types.clone();
+        lattice.positions = self.positions.clone();
+        lattice.cells = self.cells.clone();
+        lattice.boundaries = self.boundaries;
+        Ok(lattice)
+    }
+    /// Picks an edge with probability proportional to the magnitude of its coupling.
+    pub fn random_edge<R: Rng>(&self, rng: &mut R) -> Edge {
+        self.edges[rng.sample(&self.distribution)]
     }
 }
 
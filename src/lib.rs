@@ -0,0 +1,41 @@
+//! Stochastic series expansion quantum Monte Carlo for Heisenberg models on
+//! bipartite lattices.
+//!
+//! Lattices are built with [`lattice::LatticeConstructor`], directly or from
+//! the common geometries of [`geometry`] or the files of [`graph`], drawn by
+//! [`render`], sampled by [`simulation::Simulation`] on top of the raw
+//! [`state::State`] Markov chain, and cross-checked against [`ed`] on small
+//! systems. The transverse-field Ising model has its own Markov chain in
+//! [`tfim`], while [`boson`] samples hard- and soft-core bosons through their
+//! spin model.
+#![allow(non_camel_case_types, non_snake_case)]
+
+pub mod boson;
+pub mod checkpoint;
+pub mod config;
+pub mod ed;
+pub mod ensemble;
+pub mod geometry;
+pub mod graph;
+pub mod lattice;
+pub mod model;
+pub mod render;
+pub mod rng;
+pub mod simulation;
+pub mod state;
+pub mod stats;
+pub mod tempering;
+pub mod tfim;
+pub mod vertex;
+
+/// An [`std::io::ErrorKind::InvalidData`] error for a file that cannot be
+/// read.
+pub(crate) fn invalid(message: impl ToString) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, message.to_string())
+}
+
+/// An [`std::io::ErrorKind::InvalidInput`] error for parameters that cannot
+/// be sampled.
+pub(crate) fn invalid_input(message: impl ToString) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidInput, message.to_string())
+}
@@ -0,0 +1,291 @@
+use crate::lattice::{two_coloring, Edge, Even_Site_Id, Lattice, Odd_Site_Id, Site_Id};
+use crate::vertex::{vertex_legs, Bond};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fmt;
+
+/// What the levels of a site count.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Particles {
+    /// Level `l` is `S^z = l - spin`.
+    #[default]
+    Spins,
+    /// Level `l` holds `n = l` bosons, up to `2 spin`.
+    Bosons,
+}
+
+/// Spin, couplings and fields shared by every bond of the lattice.
+///
+/// Every site carries a spin `spin`, 1/2 by default. Bond `b` contributes
+/// `J_b (Delta S^z_i S^z_j + (S^+_i S^-_j + S^-_i S^+_j) / 2)` to the
+/// hamiltonian, so `delta = 1` is the Heisenberg model, `delta > 1` is
+/// easy-axis and `delta < 1` easy-plane. The fields add
+/// `-sum_i (field + (-1)^i staggered_field) S^z_i` with even sites counted
+/// positive, and are split evenly over the bonds of every site.
+///
+/// With [`Particles::Bosons`] the occupations `n_i` take the place of `S^z_i`
+/// and bond `b` contributes `J_b (Delta n_i n_j - b^+_i b_j - b^+_j b_i)`, so
+/// `J_b` is the hopping and `field` the chemical potential. The on-site
+/// repulsion adds `onsite n_i (n_i - 1) / 2`.
+///
+/// The SSE samples `C_b - H_b` on every bond, where the constant `C_b` is the
+/// smallest one that keeps every diagonal vertex weight non-negative plus
+/// `epsilon * |J_b|`; a positive `epsilon` also gives parallel spins a nonzero
+/// weight at `delta = 1`.
+///
+/// Negative couplings are ferromagnetic. Bonds of either sign may be mixed as
+/// long as [`Model::sign_free`] holds for the lattice.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Model {
+    pub particles: Particles,
+    pub spin: f64,
+    pub delta: f64,
+    pub epsilon: f64,
+    pub field: f64,
+    pub staggered_field: f64,
+    pub onsite: f64,
+}
+
+impl Default for Model {
+    fn default() -> Model {
+        Model {
+            particles: Particles::Spins,
+            spin: 0.5,
+            delta: 1.0,
+            epsilon: 0.0,
+            field: 0.0,
+            staggered_field: 0.0,
+            onsite: 0.0,
+        }
+    }
+}
+
+impl Model {
+    pub fn heisenberg() -> Model {
+        Model::default()
+    }
+    pub fn xxz(delta: f64) -> Model {
+        Model {
+            delta,
+            ..Model::default()
+        }
+    }
+    /// Spin of every site, a positive multiple of 1/2 up to 3.
+    pub fn spin(mut self, spin: f64) -> Model {
+        assert!(
+            (2.0 * spin).fract() == 0.0 && (0.5..=3.0).contains(&spin),
+            "spin must be a multiple of 1/2 between 1/2 and 3"
+        );
+        self.spin = spin;
+        self
+    }
+    /// Bosons with at most `max_occupation` per site, 1 for hard-core bosons.
+    pub fn bosons(self, max_occupation: usize) -> Model {
+        Model {
+            particles: Particles::Bosons,
+            ..self.spin(max_occupation as f64 / 2.0)
+        }
+    }
+    pub fn epsilon(mut self, epsilon: f64) -> Model {
+        assert!(epsilon >= 0.0, "epsilon must not be negative");
+        self.epsilon = epsilon;
+        self
+    }
+    pub fn field(mut self, field: f64) -> Model {
+        self.field = field;
+        self
+    }
+    pub fn staggered_field(mut self, staggered_field: f64) -> Model {
+        self.staggered_field = staggered_field;
+        self
+    }
+    /// On-site repulsion `U` of bosons.
+    pub fn onsite(mut self, onsite: f64) -> Model {
+        assert!(
+            self.particles == Particles::Bosons,
+            "the on-site repulsion needs bosons"
+        );
+        self.onsite = onsite;
+        self
+    }
+
+    /// Number of S^z states of a site, `2 spin + 1`.
+    pub fn levels(&self) -> usize {
+        (2.0 * self.spin) as usize + 1
+    }
+
+    /// `S^z` of a site in its state `level`, counted up from `-spin`, or its
+    /// occupation for bosons.
+    pub fn sz(&self, level: u8) -> f64 {
+        match self.particles {
+            Particles::Spins => level as f64 - self.spin,
+            Particles::Bosons => level as f64,
+        }
+    }
+
+    /// `<level + 1| S^+ |level>`, or `<level + 1| b^+ |level>` for bosons,
+    /// zero on the highest level.
+    pub fn raise(&self, level: u8) -> f64 {
+        if level as usize + 1 >= self.levels() {
+            return 0.0;
+        }
+        match self.particles {
+            Particles::Spins => {
+                let sz = self.sz(level);
+                (self.spin * (self.spin + 1.0) - sz * (sz + 1.0)).sqrt()
+            }
+            Particles::Bosons => (level as f64 + 1.0).sqrt(),
+        }
+    }
+
+    /// Prefactor of `S^+_i S^-_j + S^-_i S^+_j`, or of the hopping of bosons,
+    /// on a bond of coupling `coupling`.
+    pub fn off_diagonal(&self, coupling: f64) -> f64 {
+        match self.particles {
+            Particles::Spins => coupling / 2.0,
+            Particles::Bosons => -coupling,
+        }
+    }
+
+    /// Field acting on an even (`true`) or odd site.
+    pub fn site_field(&self, even: bool) -> f64 {
+        if even {
+            self.field + self.staggered_field
+        } else {
+            self.field - self.staggered_field
+        }
+    }
+
+    /// Diagonal energy of an even (`true`) or odd site in state `level`.
+    pub fn site_energy(&self, level: u8, even: bool) -> f64 {
+        let sz = self.sz(level);
+        -self.site_field(even) * sz + self.onsite * sz * (sz - 1.0) / 2.0
+    }
+
+    /// Weights of the bond vertices indexed by [`crate::vertex::vertex_state`],
+    /// and the constant `C_b`.
+    ///
+    /// `share_even` and `share_odd` are the parts of the site terms carried
+    /// by this bond.
+    pub fn bond_weights(&self, coupling: f64, share_even: f64, share_odd: f64) -> (Vec<f64>, f64) {
+        let levels = self.levels();
+        let diagonal = |even: u8, odd: u8| {
+            -coupling * self.delta * self.sz(even) * self.sz(odd)
+                - share_even * self.site_energy(even, true)
+                - share_odd * self.site_energy(odd, false)
+        };
+        let lowest = (0..levels as u8)
+            .flat_map(|even| (0..levels as u8).map(move |odd| (even, odd)))
+            .map(|(even, odd)| diagonal(even, odd))
+            .fold(f64::INFINITY, f64::min);
+        let shift = self.epsilon * coupling.abs() - lowest;
+        // the signs of the off-diagonal vertices are gauged away, see `sign_free`
+        let off_diagonal = self.off_diagonal(coupling).abs();
+        let mut weights = vec![0.0; levels.pow(4)];
+        for (state, weight) in weights.iter_mut().enumerate() {
+            let [even, odd, even_above, odd_above] = vertex_legs(state, levels);
+            if (even, odd) == (even_above, odd_above) {
+                *weight = shift + diagonal(even, odd);
+            } else if even_above == even + 1 && odd == odd_above + 1 {
+                *weight = off_diagonal * self.raise(even) * self.raise(odd_above);
+            } else if even == even_above + 1 && odd_above == odd + 1 {
+                *weight = off_diagonal * self.raise(even_above) * self.raise(odd);
+            }
+        }
+        (weights, shift)
+    }
+
+    /// Why the SSE cannot sample this model, `None` if it can: the spin must
+    /// be a multiple of 1/2 between 1/2 and 3, `epsilon` must not be negative
+    /// and every other parameter must be finite.
+    pub fn unsupported(&self) -> Option<String> {
+        if (2.0 * self.spin).fract() != 0.0 || !(0.5..=3.0).contains(&self.spin) {
+            return Some(format!("spin {} is not supported", self.spin));
+        }
+        if self.epsilon.is_nan() || self.epsilon < 0.0 {
+            return Some("epsilon must not be negative".to_string());
+        }
+        for (name, value) in [
+            ("delta", self.delta),
+            ("epsilon", self.epsilon),
+            ("field", self.field),
+            ("staggered field", self.staggered_field),
+            ("on-site repulsion", self.onsite),
+        ] {
+            if !value.is_finite() {
+                return Some(format!("{} {} is not finite", name, value));
+            }
+        }
+        None
+    }
+
+    /// Whether every configuration of the SSE on `lattice` has a positive
+    /// weight, see [`Model::sign_problem`].
+    pub fn sign_free(&self, lattice: &Lattice) -> bool {
+        self.sign_problem(lattice).is_none()
+    }
+
+    /// A cycle of bonds of `lattice` that gives some configurations of the SSE
+    /// a negative weight, `None` if there is none.
+    ///
+    /// An off-diagonal operator on bond `<ij>` carries the sign of
+    /// `-off_diagonal(J_b)`, and a plaquette operator that flips one of its
+    /// bonds a negative sign. Rotating site `i` by `s_i = +-1` multiplies the
+    /// bond by `s_i s_j`, so the signs drop out exactly when there is a choice
+    /// of `s_i` that makes every bond positive. Otherwise there is a cycle
+    /// with an odd number of negative bonds, which a worldline winding around
+    /// it picks up.
+    pub fn sign_problem(&self, lattice: &Lattice) -> Option<SignProblem> {
+        let site = |edge: &Edge| (edge.even.0, lattice.num_even + edge.odd.0);
+        // `(i, j, flip)`: the bond is positive when `s_i != s_j` equals `flip`
+        let mut bonds = Vec::new();
+        let mut ids = Vec::new();
+        for edge in lattice.edges.iter() {
+            let off_diagonal = self.off_diagonal(edge.coupling);
+            if off_diagonal != 0.0 {
+                let (i, j) = site(edge);
+                bonds.push((i, j, off_diagonal > 0.0));
+                ids.push(Bond::Edge(edge.id));
+            }
+        }
+        for plaquette in lattice.plaquettes.iter().filter(|p| p.coupling != 0.0) {
+            for edge in plaquette.edges.iter() {
+                let (i, j) = site(edge);
+                bonds.push((i, j, true));
+                ids.push(Bond::Plaquette(plaquette.id));
+            }
+        }
+        let (sites, cycle) = two_coloring(lattice.num_even + lattice.num_odd, &bonds).err()?;
+        Some(SignProblem {
+            sites: sites
+                .into_iter()
+                .map(|i| match i.checked_sub(lattice.num_even) {
+                    Some(odd) => Site_Id::Odd(Odd_Site_Id(odd)),
+                    None => Site_Id::Even(Even_Site_Id(i)),
+                })
+                .collect(),
+            bonds: cycle.into_iter().map(|b| ids[b]).collect(),
+        })
+    }
+}
+
+/// A cycle of bonds with an odd number of negative off-diagonal vertices
+/// whichever way the sites are rotated, found by [`Model::sign_problem`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SignProblem {
+    /// Sites around the cycle.
+    pub sites: Vec<Site_Id>,
+    /// The edge or plaquette joining each site to the next, the last one
+    /// closing the cycle.
+    pub bonds: Vec<Bond>,
+}
+
+impl fmt::Display for SignProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "sign problem around the cycle of bonds {:?}", self.bonds)
+    }
+}
+
+impl Error for SignProblem {}
@@ -0,0 +1,336 @@
+//! Pictures of lattices, to check by eye what a constructor built.
+//!
+//! Both renderers draw a [`Graph`], so sites keep the labels given to
+//! [`Graph::from_labelled_lattice`]:
+//!
+//! ```
+//! use montecarlo::graph::Graph;
+//! use montecarlo::lattice::make_latice;
+//! use montecarlo::render;
+//!
+//! let graph = Graph::from_lattice(&make_latice(4, 4, 0.5).unwrap());
+//! let picture = render::svg(&graph);
+//! assert!(picture.starts_with("<svg"));
+//! ```
+//!
+//! Even sites are blue and odd sites red. Every bond class has its own color,
+//! bonds without one are black. The width of a bond grows with the magnitude
+//! of its coupling, ferromagnetic bonds are dashed and bonds without coupling
+//! dotted.
+
+use crate::graph::{Graph, GraphEdge, Sublattice};
+use std::collections::HashMap;
+use std::fmt::Write;
+
+const EVEN: &str = "#4477aa";
+const ODD: &str = "#ee6677";
+/// Sites whose sublattice is not known.
+const UNCOLORED: &str = "#bbbbbb";
+const CLASSES: [&str; 6] = [
+    "#228833", "#ccbb44", "#66ccee", "#aa3377", "#ee7733", "#009988",
+];
+const UNCLASSIFIED: &str = "#000000";
+
+/// Length in pixels of a unit of the positions.
+const SCALE: f64 = 60.0;
+const MARGIN: f64 = 30.0;
+const RADIUS: f64 = 10.0;
+
+fn site_color(sublattice: Option<Sublattice>) -> &'static str {
+    match sublattice {
+        Some(Sublattice::Even) => EVEN,
+        Some(Sublattice::Odd) => ODD,
+        None => UNCOLORED,
+    }
+}
+
+/// The bond classes in the order they first appear.
+fn classes(graph: &Graph) -> Vec<&str> {
+    let mut classes = Vec::new();
+    for class in graph.edges.iter().filter_map(|e| e.class.as_deref()) {
+        if !classes.contains(&class) {
+            classes.push(class);
+        }
+    }
+    classes
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Line {
+    Solid,
+    /// Ferromagnetic bonds.
+    Dashed,
+    /// Bonds without coupling.
+    Dotted,
+}
+
+/// How an edge is drawn.
+struct Style {
+    color: &'static str,
+    width: f64,
+    line: Line,
+    coupling: f64,
+}
+
+impl Style {
+    /// The style of `edge`, whose width is measured against the `largest`
+    /// magnitude of the couplings.
+    fn new(graph: &Graph, classes: &[&str], largest: f64, edge: &GraphEdge) -> Style {
+        let coupling = graph.coupling(edge);
+        let color = match edge.class.as_deref() {
+            Some(class) => {
+                let index = classes.iter().position(|&c| c == class).unwrap();
+                CLASSES[index % CLASSES.len()]
+            }
+            None => UNCLASSIFIED,
+        };
+        let line = if coupling == 0.0 {
+            Line::Dotted
+        } else if coupling < 0.0 {
+            Line::Dashed
+        } else {
+            Line::Solid
+        };
+        Style {
+            color,
+            width: 1.0 + 3.0 * coupling.abs() / largest.max(f64::MIN_POSITIVE),
+            line,
+            coupling,
+        }
+    }
+
+    fn tooltip(&self, edge: &GraphEdge) -> String {
+        match &edge.class {
+            Some(class) => format!("{} J = {}", class, self.coupling),
+            None => format!("J = {}", self.coupling),
+        }
+    }
+}
+
+/// The largest magnitude of the couplings of the edges.
+fn largest_coupling(graph: &Graph) -> f64 {
+    graph
+        .edges
+        .iter()
+        .map(|e| graph.coupling(e).abs())
+        .fold(0.0, f64::max)
+}
+
+/// Quotes `text` for DOT.
+fn quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Escapes `text` for XML.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Where a site is drawn, in units of the positions: 3D positions are seen
+/// obliquely so that layers do not hide each other.
+fn project(position: [f64; 3]) -> [f64; 2] {
+    [
+        position[0] + 0.4 * position[2],
+        position[1] + 0.25 * position[2],
+    ]
+}
+
+/// Projected position of every site, on a circle if the graph has none.
+fn layout(graph: &Graph) -> Vec<[f64; 2]> {
+    let sites = graph.sites.len();
+    let radius = (sites as f64 / std::f64::consts::TAU).max(1.0);
+    graph
+        .sites
+        .iter()
+        .enumerate()
+        .map(|(i, site)| match site.position {
+            Some(position) => project(position),
+            None => {
+                let angle = std::f64::consts::TAU * i as f64 / sites as f64;
+                [radius * angle.cos(), radius * angle.sin()]
+            }
+        })
+        .collect()
+}
+
+/// The graph in the DOT language of Graphviz. Sites with a position are
+/// pinned there, render them with `neato -n`.
+pub fn dot(graph: &Graph) -> String {
+    let classes = classes(graph);
+    let largest = largest_coupling(graph);
+    let mut dot = String::from("graph lattice {\n");
+    dot.push_str("  node [shape=circle, style=filled, fontcolor=white];\n");
+    for site in graph.sites.iter() {
+        write!(
+            dot,
+            "  {} [fillcolor={}",
+            quote(&site.label),
+            quote(site_color(site.sublattice))
+        )
+        .unwrap();
+        if let Some(position) = site.position {
+            let [x, y] = project(position);
+            write!(dot, ", pos=\"{},{}!\"", x * SCALE, y * SCALE).unwrap();
+        }
+        dot.push_str("];\n");
+    }
+    for edge in graph.edges.iter() {
+        let style = Style::new(graph, &classes, largest, edge);
+        write!(
+            dot,
+            "  {} -- {} [color={}, penwidth={:.2}, tooltip={}",
+            quote(&edge.a),
+            quote(&edge.b),
+            quote(style.color),
+            style.width,
+            quote(&style.tooltip(edge))
+        )
+        .unwrap();
+        match style.line {
+            Line::Solid => {}
+            Line::Dashed => dot.push_str(", style=dashed"),
+            Line::Dotted => dot.push_str(", style=dotted"),
+        }
+        dot.push_str("];\n");
+    }
+    dot.push_str("}\n");
+    dot
+}
+
+/// The graph as a standalone SVG picture with a legend of the bond classes.
+///
+/// Bonds that close a periodic boundary run across the whole picture; they
+/// are drawn faded when longer than twice the shortest bond.
+pub fn svg(graph: &Graph) -> String {
+    let classes = classes(graph);
+    let largest = largest_coupling(graph);
+    let points = layout(graph);
+    let index: HashMap<&str, usize> = graph
+        .sites
+        .iter()
+        .enumerate()
+        .map(|(i, s)| (s.label.as_str(), i))
+        .collect();
+    let ends: Vec<Option<([f64; 2], [f64; 2])>> = graph
+        .edges
+        .iter()
+        .map(|e| {
+            Some((
+                points[*index.get(e.a.as_str())?],
+                points[*index.get(e.b.as_str())?],
+            ))
+        })
+        .collect();
+    let length = |&(a, b): &([f64; 2], [f64; 2])| (a[0] - b[0]).hypot(a[1] - b[1]);
+    let shortest = ends
+        .iter()
+        .flatten()
+        .map(length)
+        .filter(|&l| l > 0.0)
+        .fold(f64::INFINITY, f64::min);
+
+    let (mut low, mut high) = ([f64::INFINITY; 2], [f64::NEG_INFINITY; 2]);
+    for point in points.iter() {
+        for axis in 0..2 {
+            low[axis] = low[axis].min(point[axis]);
+            high[axis] = high[axis].max(point[axis]);
+        }
+    }
+    if points.is_empty() {
+        (low, high) = ([0.0; 2], [0.0; 2]);
+    }
+    let width = (high[0] - low[0]) * SCALE + 2.0 * MARGIN;
+    let legend = 20.0 * classes.len() as f64;
+    let height = (high[1] - low[1]) * SCALE + 2.0 * MARGIN + legend;
+    // the y axis of SVG points down
+    let pixel = |[x, y]: [f64; 2]| {
+        [
+            MARGIN + (x - low[0]) * SCALE,
+            MARGIN + (high[1] - y) * SCALE,
+        ]
+    };
+
+    let mut svg = String::new();
+    writeln!(
+        svg,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{:.1}\" height=\"{:.1}\" \
+         viewBox=\"0 0 {:.1} {:.1}\" font-family=\"sans-serif\">",
+        width, height, width, height
+    )
+    .unwrap();
+    writeln!(svg, "<rect width=\"100%\" height=\"100%\" fill=\"white\"/>").unwrap();
+    for (edge, ends) in graph.edges.iter().zip(ends.iter()) {
+        let Some(ends) = ends else { continue };
+        let style = Style::new(graph, &classes, largest, edge);
+        let ([x1, y1], [x2, y2]) = (pixel(ends.0), pixel(ends.1));
+        write!(
+            svg,
+            "<line x1=\"{:.1}\" y1=\"{:.1}\" x2=\"{:.1}\" y2=\"{:.1}\" stroke=\"{}\" \
+             stroke-width=\"{:.2}\"",
+            x1, y1, x2, y2, style.color, style.width
+        )
+        .unwrap();
+        match style.line {
+            Line::Solid => {}
+            Line::Dashed => svg.push_str(" stroke-dasharray=\"6 4\""),
+            Line::Dotted => svg.push_str(" stroke-dasharray=\"1 4\""),
+        }
+        if length(ends) > 2.0 * shortest {
+            svg.push_str(" stroke-opacity=\"0.3\"");
+        }
+        writeln!(
+            svg,
+            "><title>{}</title></line>",
+            escape(&style.tooltip(edge))
+        )
+        .unwrap();
+    }
+    for (site, &point) in graph.sites.iter().zip(points.iter()) {
+        let [x, y] = pixel(point);
+        writeln!(
+            svg,
+            "<circle cx=\"{:.1}\" cy=\"{:.1}\" r=\"{}\" fill=\"{}\" stroke=\"black\"/>",
+            x,
+            y,
+            RADIUS,
+            site_color(site.sublattice)
+        )
+        .unwrap();
+        writeln!(
+            svg,
+            "<text x=\"{:.1}\" y=\"{:.1}\" font-size=\"9\" text-anchor=\"middle\" \
+             dominant-baseline=\"central\" fill=\"white\">{}</text>",
+            x,
+            y,
+            escape(&site.label)
+        )
+        .unwrap();
+    }
+    for (i, class) in classes.iter().enumerate() {
+        let y = height - legend + 20.0 * i as f64 + 10.0;
+        writeln!(
+            svg,
+            "<line x1=\"{:.1}\" y1=\"{:.1}\" x2=\"{:.1}\" y2=\"{:.1}\" stroke=\"{}\" \
+             stroke-width=\"3\"/>",
+            MARGIN,
+            y,
+            MARGIN + 20.0,
+            y,
+            CLASSES[i % CLASSES.len()]
+        )
+        .unwrap();
+        writeln!(
+            svg,
+            "<text x=\"{:.1}\" y=\"{:.1}\" font-size=\"12\" dominant-baseline=\"central\">{}</text>",
+            MARGIN + 28.0,
+            y,
+            escape(class)
+        )
+        .unwrap();
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
@@ -0,0 +1,16 @@
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+
+/// The generator used for every Markov chain and bootstrap.
+pub type ChainRng = ChaCha8Rng;
+
+/// Derives the generator for chain number `chain` from the top level seed.
+///
+/// Every chain gets its own ChaCha stream, so chains are independent of each
+/// other and of the order they are run in, and a given `(seed, chain)` pair
+/// always reproduces the same sequence.
+pub fn chain_rng(seed: u64, chain: u64) -> ChainRng {
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    rng.set_stream(chain);
+    rng
+}
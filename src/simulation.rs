@@ -0,0 +1,337 @@
+use crate::checkpoint::Checkpoint;
+use crate::ensemble::Ensemble;
+use crate::lattice::Lattice;
+use crate::model::Model;
+use crate::rng::{chain_rng, ChainRng};
+use crate::state::{Sample, State};
+use crate::stats;
+use crate::tfim::{IsingState, TransverseIsing};
+use crate::vertex::Vertices;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+/// Monte Carlo budget and error analysis of a chain.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MonteCarlo {
+    /// Number of measurements after thermalization
+    pub samples: usize,
+    /// Loop updates per Monte Carlo step
+    pub nloop: usize,
+    pub bin_size: usize,
+    pub bootstraps: usize,
+    /// Top level seed, every chain derives its own stream from it
+    pub seed: u64,
+    /// Independent replicas per parameter point, merged into one estimate
+    pub chains: usize,
+    /// Worker threads for the replicas and parameter points, 0 uses every core
+    pub threads: usize,
+}
+
+impl Default for MonteCarlo {
+    fn default() -> MonteCarlo {
+        MonteCarlo {
+            samples: 10000,
+            nloop: 40,
+            bin_size: 100,
+            bootstraps: 1000,
+            seed: 42,
+            chains: 1,
+            threads: 0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Observable {
+    Energy,
+    StaggeredMagnetization,
+    Magnetization,
+}
+
+impl Observable {
+    pub const ALL: [Observable; 3] = [
+        Observable::Energy,
+        Observable::StaggeredMagnetization,
+        Observable::Magnetization,
+    ];
+}
+
+impl FromStr for Observable {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Observable, String> {
+        match s {
+            "energy" => Ok(Observable::Energy),
+            "staggered-magnetization" => Ok(Observable::StaggeredMagnetization),
+            "magnetization" => Ok(Observable::Magnetization),
+            _ => Err(format!("unknown observable {}", s)),
+        }
+    }
+}
+
+/// Time series of a single observable, possibly from several independent chains.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Accumulator {
+    samples: Vec<f64>,
+    /// ends of every chain but the last one in `samples`
+    ends: Vec<usize>,
+}
+
+impl Accumulator {
+    pub fn new() -> Accumulator {
+        Accumulator::default()
+    }
+    pub fn push(&mut self, value: f64) {
+        self.samples.push(value);
+    }
+    /// All samples, chain after chain.
+    pub fn samples(&self) -> &[f64] {
+        &self.samples
+    }
+    pub fn chains(&self) -> impl Iterator<Item = &[f64]> {
+        let starts = std::iter::once(0).chain(self.ends.iter().copied());
+        let ends = self.ends.iter().copied().chain(std::iter::once(self.len()));
+        starts
+            .zip(ends)
+            .map(|(start, end)| &self.samples[start..end])
+    }
+    /// Appends the chains of `other`, which must be independent of the ones already present.
+    pub fn merge(&mut self, other: &Accumulator) {
+        if !self.is_empty() {
+            self.ends.push(self.len());
+        }
+        let offset = self.len();
+        self.ends.extend(other.ends.iter().map(|end| end + offset));
+        self.samples.extend_from_slice(&other.samples);
+    }
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+    pub fn mean(&self) -> f64 {
+        stats::calc_mean(&self.samples)
+    }
+    /// Bins of every chain; a bin never straddles two chains.
+    pub fn binned(&self, bin_size: usize) -> Vec<f64> {
+        self.chains()
+            .flat_map(|chain| stats::bin(chain, bin_size))
+            .collect()
+    }
+    /// Binned bootstrap `(mean, error)`; binning absorbs the autocorrelation of
+    /// each chain and the bins of independent chains are resampled together.
+    pub fn estimate<R: Rng>(&self, bin_size: usize, bootstraps: usize, rng: &mut R) -> (f64, f64) {
+        stats::bootstrap(&self.binned(bin_size), bootstraps, rng)
+    }
+}
+
+/// Everything measured by a finished [`Simulation`].
+#[derive(Clone, Debug)]
+pub struct Measurements {
+    pub energy: Accumulator,
+    pub staggered_magnetization: Accumulator,
+    pub magnetization: Accumulator,
+    pub monte_carlo: MonteCarlo,
+    /// The chain's generator after sampling, used for the bootstrap so the
+    /// error bars are reproducible too.
+    pub rng: ChainRng,
+}
+
+impl Measurements {
+    pub fn new(monte_carlo: MonteCarlo, rng: ChainRng) -> Measurements {
+        Measurements {
+            energy: Accumulator::new(),
+            staggered_magnetization: Accumulator::new(),
+            magnetization: Accumulator::new(),
+            monte_carlo,
+            rng,
+        }
+    }
+    pub fn push(&mut self, sample: &Sample) {
+        self.energy.push(sample.energy);
+        self.staggered_magnetization
+            .push(sample.staggered_magnetization);
+        self.magnetization.push(sample.magnetization);
+    }
+    pub fn observable(&self, observable: Observable) -> &Accumulator {
+        match observable {
+            Observable::Energy => &self.energy,
+            Observable::StaggeredMagnetization => &self.staggered_magnetization,
+            Observable::Magnetization => &self.magnetization,
+        }
+    }
+    /// Pools independent chains of the same parameters, keeping the generator
+    /// of the first one for the bootstrap.
+    pub fn merge(chains: Vec<Measurements>) -> Measurements {
+        let mut chains = chains.into_iter();
+        let mut merged = chains.next().expect("No chains to merge");
+        for chain in chains {
+            merged.energy.merge(&chain.energy);
+            merged
+                .staggered_magnetization
+                .merge(&chain.staggered_magnetization);
+            merged.magnetization.merge(&chain.magnetization);
+        }
+        merged
+    }
+    pub fn estimate(&mut self, observable: Observable) -> (f64, f64) {
+        let mc = &self.monte_carlo;
+        let accumulator = match observable {
+            Observable::Energy => &self.energy,
+            Observable::StaggeredMagnetization => &self.staggered_magnetization,
+            Observable::Magnetization => &self.magnetization,
+        };
+        accumulator.estimate(mc.bin_size, mc.bootstraps, &mut self.rng)
+    }
+}
+
+/// A single Markov chain: thermalization followed by `samples` measurements.
+///
+/// ```no_run
+/// use montecarlo::lattice::make_latice;
+/// use montecarlo::simulation::{Observable, Simulation};
+///
+/// let mut measurements = Simulation::new(make_latice(4, 4, 0.5).unwrap(), 8.0).run().unwrap();
+/// let (energy, error) = measurements.estimate(Observable::Energy);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Simulation {
+    lattice: Lattice,
+    model: Model,
+    transverse_ising: Option<TransverseIsing>,
+    beta: f64,
+    pub(crate) monte_carlo: MonteCarlo,
+    chain: u64,
+    checkpoint: Option<(PathBuf, usize)>,
+}
+
+impl Simulation {
+    pub fn new(lattice: Lattice, beta: f64) -> Simulation {
+        Simulation {
+            lattice,
+            model: Model::default(),
+            transverse_ising: None,
+            beta,
+            monte_carlo: MonteCarlo::default(),
+            chain: 0,
+            checkpoint: None,
+        }
+    }
+    pub fn model(mut self, model: Model) -> Simulation {
+        self.model = model;
+        self
+    }
+    /// Samples the transverse-field Ising model with [`IsingState`] instead of
+    /// the XXZ model, which does not support checkpoints.
+    pub fn transverse_ising(mut self, model: TransverseIsing) -> Simulation {
+        self.transverse_ising = Some(model);
+        self
+    }
+    pub fn monte_carlo(mut self, monte_carlo: MonteCarlo) -> Simulation {
+        self.monte_carlo = monte_carlo;
+        self
+    }
+    /// Stream of the top level seed used by this chain, see [`chain_rng`].
+    pub fn chain(mut self, chain: u64) -> Simulation {
+        self.chain = chain;
+        self
+    }
+    /// Checkpoints to `path` every `every` samples and resumes from it if it exists.
+    pub fn checkpoint(mut self, path: impl Into<PathBuf>, every: usize) -> Simulation {
+        self.checkpoint = Some((path.into(), every));
+        self
+    }
+
+    /// `monte_carlo.chains` independent copies of this chain.
+    ///
+    /// Replica `i` uses stream `chain + i * 2^32`, so replica 0 is the chain
+    /// itself. With more than one replica the checkpoint of replica `i` gets
+    /// the suffix `.r<i>`.
+    pub fn replicas(&self) -> Vec<Simulation> {
+        let count = self.monte_carlo.chains.max(1);
+        (0..count)
+            .map(|i| {
+                let mut replica = self.clone();
+                replica.chain = self.chain + ((i as u64) << 32);
+                if count > 1 {
+                    if let Some((path, _)) = &mut replica.checkpoint {
+                        path.as_mut_os_string().push(format!(".r{}", i));
+                    }
+                }
+                replica
+            })
+            .collect()
+    }
+
+    /// Runs the [`Simulation::replicas`] on `monte_carlo.threads` threads and
+    /// pools their measurements, see [`Ensemble`].
+    pub fn run(&self) -> io::Result<Measurements> {
+        let mut ensemble = Ensemble::new();
+        ensemble.push(self.clone());
+        Ok(ensemble.run()?.remove(0))
+    }
+
+    /// Runs this chain alone, whatever `monte_carlo.chains`.
+    pub(crate) fn run_chain(&self) -> io::Result<Measurements> {
+        let mc = &self.monte_carlo;
+        if let Some(model) = self.transverse_ising {
+            return self.run_transverse_ising(model);
+        }
+        Vertices::check(&self.lattice, &self.model)?;
+        let start = || {
+            let mut rng = chain_rng(mc.seed, self.chain);
+            let mut s = State::with_model(&self.lattice, self.model, 10, &mut rng);
+            s.thermalize(self.beta, &mut rng);
+            Checkpoint::new(s, rng, self.beta, mc.nloop)
+        };
+        let mut run = match &self.checkpoint {
+            Some((path, _)) if path.exists() => {
+                let run = Checkpoint::load(path)?;
+                run.check_parameters(&self.lattice, &self.model, self.beta)?;
+                run
+            }
+            _ => start(),
+        };
+        match &self.checkpoint {
+            Some((path, every)) => run.run(mc.samples, *every, path)?,
+            None => {
+                while run.samples.len() < mc.samples {
+                    let sample = run.state.sample(run.nloop, run.beta, &mut run.rng);
+                    run.samples.push(sample);
+                }
+            }
+        }
+        let mut measurements = Measurements::new(mc.clone(), run.rng);
+        for sample in run.samples.iter() {
+            measurements.push(sample);
+        }
+        Ok(measurements)
+    }
+
+    fn run_transverse_ising(&self, model: TransverseIsing) -> io::Result<Measurements> {
+        if self.checkpoint.is_some() {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "the transverse-field Ising model cannot be checkpointed",
+            ));
+        }
+        let mc = &self.monte_carlo;
+        let mut rng = chain_rng(mc.seed, self.chain);
+        let mut s = IsingState::new(&self.lattice, model, 10, &mut rng);
+        s.thermalize(self.beta, &mut rng);
+        let mut samples = Vec::with_capacity(mc.samples);
+        for _ in 0..mc.samples {
+            samples.push(s.sample(self.beta, &mut rng));
+        }
+        let mut measurements = Measurements::new(mc.clone(), rng);
+        for sample in samples.iter() {
+            measurements.push(sample);
+        }
+        Ok(measurements)
+    }
+}
@@ -1,11 +1,36 @@
-use crate::lattice::{
-    Bipartite_Id_Vec, Edge, EdgeType, Even_Site_Id, Lattice, Odd_Site_Id, Site_Id,
-};
-use id_collections::{id_type, Id, IdVec};
-use rand::{rngs::ThreadRng, Rng};
+use crate::lattice::{Bipartite_Id_Vec, Edge, Lattice, Plaquette, Site_Id};
+use crate::model::Model;
+use crate::vertex::{Bond, Vertices};
+use id_collections::{id_type, IdVec};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
 
 #[id_type]
-pub struct OperatorId(usize);
+pub struct OperatorId(pub usize);
+
+/// Operator string of the SSE, where `None` is an identity, shared by
+/// [`State`] and [`crate::tfim::IsingState`].
+pub type OperatorString<Op> = IdVec<OperatorId, Option<Op>>;
+
+/// An operator string of `m` identities.
+pub fn identities<Op>(m: usize) -> OperatorString<Op> {
+    let mut path = IdVec::new();
+    for _ in 0..m {
+        let _ = path.push(None);
+    }
+    path
+}
+
+/// Appends identities until the `n` operators fill at most 90% of `path`,
+/// returning whether the string grew.
+pub fn grow<Op>(path: &mut OperatorString<Op>, n: usize) -> bool {
+    let mut grown = false;
+    while n > path.len() * 9 / 10 {
+        let _ = path.push(None);
+        grown = true;
+    }
+    grown
+}
 
 #[derive(PartialEq, Eq, Clone, Copy, Debug)]
 pub enum OperatorType {
@@ -25,17 +50,107 @@ impl OperatorType {
 #[derive(Clone, Debug)]
 pub struct Operator {
     pub operator_type: OperatorType,
+    /// The bond of a bond operator, or the first bond of a plaquette operator.
     pub edge: Edge,
-    pub even_in_id: OperatorId,
-    pub odd_out_id: OperatorId,
+    /// Set for plaquette operators, whose second bond carries legs 4 to 7.
+    pub plaquette: Option<Plaquette>,
+    /// Level `S^z + S` on every leg, see [`crate::vertex`], legs 4 to 7 are
+    /// only used by plaquettes.
+    pub legs: [u8; 8],
+    /// The operator each leg is connected to: the previous operator on the
+    /// even and odd site for legs 0 and 1, the next one for legs 2 and 3, and
+    /// the same for legs 4 to 7 of a plaquette.
+    pub links: [OperatorId; 8],
+}
+
+impl Operator {
+    pub fn diagonal(edge: Edge, even: u8, odd: u8) -> Operator {
+        Operator {
+            operator_type: OperatorType::D,
+            edge,
+            plaquette: None,
+            legs: [even, odd, even, odd, 0, 0, 0, 0],
+            links: [OperatorId(usize::MAX); 8],
+        }
+    }
+    /// A diagonal plaquette operator on the levels `current`.
+    pub fn diagonal_plaquette(plaquette: Plaquette, current: &Bipartite_Id_Vec<u8>) -> Operator {
+        let mut legs = [0; 8];
+        for (offset, edge) in plaquette.edges.iter().enumerate() {
+            let (even, odd) = (current.even[edge.even], current.odd[edge.odd]);
+            legs[4 * offset..4 * offset + 4].copy_from_slice(&[even, odd, even, odd]);
+        }
+        Operator {
+            operator_type: OperatorType::D,
+            edge: plaquette.edges[0],
+            plaquette: Some(plaquette),
+            legs,
+            links: [OperatorId(usize::MAX); 8],
+        }
+    }
+    /// The bonds of the operator together with their first leg.
+    pub fn bonds(&self) -> impl Iterator<Item = (usize, Edge)> + '_ {
+        let second = self.plaquette.map(|p| (4, p.edges[1]));
+        std::iter::once((0, self.edge)).chain(second)
+    }
+    pub fn num_legs(&self) -> usize {
+        if self.plaquette.is_some() {
+            8
+        } else {
+            4
+        }
+    }
+    /// The site of `leg`.
+    pub fn site(&self, leg: usize) -> Site_Id {
+        let edge = if leg < 4 {
+            self.edge
+        } else {
+            self.plaquette.unwrap().edges[1]
+        };
+        if leg & 1 == 0 {
+            Site_Id::Even(edge.even)
+        } else {
+            Site_Id::Odd(edge.odd)
+        }
+    }
+    /// The leg below the operator on `site`, if it acts on it.
+    pub fn leg_below(&self, site: Site_Id) -> Option<usize> {
+        self.bonds().find_map(|(offset, edge)| match site {
+            Site_Id::Even(even) if edge.even == even => Some(offset),
+            Site_Id::Odd(odd) if edge.odd == odd => Some(offset + 1),
+            _ => None,
+        })
+    }
+    pub(crate) fn update_type(&mut self) {
+        let diagonal = self
+            .bonds()
+            .all(|(offset, _)| self.legs[offset..offset + 2] == self.legs[offset + 2..offset + 4]);
+        self.operator_type = if diagonal {
+            OperatorType::D
+        } else {
+            OperatorType::OD
+        };
+    }
+}
+
+/// Estimators of a single Monte Carlo step, totals over the lattice.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Sample {
+    pub energy: f64,
+    /// Time average of `|sum_i (-1)^i S^z_i|`, even sites counted positive.
+    pub staggered_magnetization: f64,
+    pub magnetization: f64,
 }
 
 #[derive(Clone, Debug)]
 pub struct State {
-    pub alpha: Bipartite_Id_Vec<bool>,
-    pub path: IdVec<OperatorId, Option<Operator>>,
+    /// Level `S^z + S` of every site at the start of the operator string.
+    pub alpha: Bipartite_Id_Vec<u8>,
+    pub path: OperatorString<Operator>,
     pub n: usize,
     pub latice: Lattice,
+    pub model: Model,
+    pub vertices: Vertices,
 }
 
 impl State {
@@ -47,32 +162,17 @@ impl State {
                 OperatorId((idx.0 + self.path.len() - d) % self.path.len())
             };
             if let Some(ref op) = self.path[idx2] {
-                match site {
-                    Site_Id::Even(even) => {
-                        if op.edge.even == even {
-                            return Some(idx2);
-                        }
-                    }
-                    Site_Id::Odd(odd) => {
-                        if op.edge.odd == odd {
-                            return Some(idx2);
-                        }
-                    }
+                if op.leg_below(site).is_some() {
+                    return Some(idx2);
                 }
             }
         }
         None
     }
 
-    pub fn insert_diag(&mut self, edge: Edge, idx: OperatorId) {
+    pub fn insert_diag(&mut self, op: Operator, idx: OperatorId) {
         assert!(self.path[idx].is_none(), "idx must be empty");
         self.n += 1;
-        let op = Operator {
-            operator_type: OperatorType::D,
-            edge,
-            even_in_id: OperatorId(usize::MAX),
-            odd_out_id: OperatorId(usize::MAX),
-        };
         self.path[idx] = Some(op);
     }
     pub fn delete(&mut self, idx: OperatorId) {
@@ -80,187 +180,243 @@ impl State {
         self.path[idx] = None;
         self.n -= 1;
     }
+    /// Panics if the operator string is inconsistent, see [`State::check`].
     pub fn verify(&self) {
-        //check that every connetion has no other operators interupting it in the same site
+        if let Err(error) = self.check() {
+            panic!("{}", error);
+        }
+    }
+    /// Checks that every leg is linked to the neighbouring operator on its
+    /// site, that every operator has a positive weight and the levels below
+    /// it, and that the levels return to `alpha` at the end of the string.
+    pub fn check(&self) -> Result<(), String> {
         for (idx, op) in self.path.iter() {
             if let Some(op) = op {
-                assert_eq!(
-                    self.next_operator(Site_Id::Odd(op.edge.odd), idx, true)
-                        .unwrap(),
-                    op.odd_out_id,
-                    "odd out trace error"
-                );
-                assert_eq!(
-                    self.next_operator(Site_Id::Even(op.edge.even), idx, false)
-                        .unwrap(),
-                    op.even_in_id,
-                    "even in trace error"
-                );
+                for leg in 0..op.num_legs() {
+                    let next = self.next_operator(op.site(leg), idx, leg & 2 == 2);
+                    if next != Some(op.links[leg]) {
+                        return Err(format!("op {:?} leg {} trace error", idx, leg));
+                    }
+                }
+                if self.vertices.weight(op) <= 0.0 {
+                    return Err(format!("op {:?} has zero weight", idx));
+                }
+                let mut checked = op.clone();
+                checked.update_type();
+                if checked.operator_type != op.operator_type {
+                    return Err(format!("op {:?} type error", idx));
+                }
             }
         }
-        //check that operators sit on oposing spins and that alpha loops
         let mut current = self.alpha.clone();
-        for (i, op) in self.path.iter() {
+        for (idx, op) in self.path.iter() {
             if let Some(op) = op {
-                assert_ne!(
-                    current.even[op.edge.even], current.odd[op.edge.odd],
-                    "op {:?} not oposing spins",
-                    i
-                );
-                if op.operator_type == OperatorType::OD {
-                    current.even[op.edge.even] ^= true;
-                    current.odd[op.edge.odd] ^= true;
+                for (offset, edge) in op.bonds() {
+                    if [current.even[edge.even], current.odd[edge.odd]]
+                        != op.legs[offset..offset + 2]
+                    {
+                        return Err(format!(
+                            "op {:?} legs do not match the levels below it",
+                            idx
+                        ));
+                    }
+                    current.even[edge.even] = op.legs[offset + 2];
+                    current.odd[edge.odd] = op.legs[offset + 3];
                 }
             }
         }
-        assert_eq!(current, self.alpha, "alpha not a loop");
-        //check that counts of are correct
-        let mut n = 0;
-        for (_, op) in self.path.iter() {
-            if op.is_some() {
-                n += 1;
-            }
+        if current != self.alpha {
+            return Err("alpha not a loop".to_string());
+        }
+        let n = self.path.iter().filter(|(_, op)| op.is_some()).count();
+        if n != self.n {
+            return Err(format!("n count error, {} operators but n = {}", n, self.n));
         }
-        assert_eq!(n, self.n, "n count error");
+        Ok(())
     }
-    pub fn directed_loop_update(&mut self, start: OperatorId) -> usize {
+    /// Moves the loop head into `start` through `start_leg`, raising
+    /// (`raise`) or lowering the level of that leg, and follows it until
+    /// it closes, returning the number of vertices visited.
+    ///
+    /// The change travels along the loop like a conserved current, so the
+    /// head always undoes it when it comes back to `start_leg`.
+    pub fn directed_loop_update<R: Rng>(
+        &mut self,
+        start: OperatorId,
+        start_leg: usize,
+        raise: bool,
+        rng: &mut R,
+    ) -> usize {
+        let change = |level: &mut u8, raise: bool| {
+            if raise {
+                *level += 1
+            } else {
+                *level -= 1
+            }
+        };
         let mut idx = start;
+        let mut leg = start_leg;
+        let mut raise = raise;
         let mut len = 0;
         loop {
-            //going up
-            let next = self.path[idx].as_ref().unwrap().odd_out_id;
-            if next <= idx {
-                self.alpha.odd[self.path[idx].as_ref().unwrap().edge.odd] ^= true;
+            let op = self.path[idx].as_mut().unwrap();
+            let exit = self.vertices.exit(op, leg, raise, rng.gen());
+            //S^z is conserved, so the exit changes the other way on the same side
+            //and a bounce leaves the vertex as it is
+            if exit != leg {
+                change(&mut op.legs[leg], raise);
+                change(&mut op.legs[exit], raise ^ (exit & 2 == leg & 2));
             }
-            idx = next;
-
-            self.path[idx].as_mut().unwrap().operator_type.flip();
-
-            //going down
-            let next = self.path[idx].as_ref().unwrap().even_in_id;
-            if next >= idx {
-                self.alpha.even[self.path[idx].as_ref().unwrap().edge.even] ^= true;
+            raise ^= exit & 2 == leg & 2;
+            op.update_type();
+            len += 1;
+            if idx == start && exit == start_leg {
+                return len;
+            }
+            let next = op.links[exit];
+            let site = op.site(exit);
+            let above = exit & 2 == 2;
+            //crossing the boundary in imaginary time changes the initial level
+            if (above && next <= idx) || (!above && next >= idx) {
+                change(self.alpha.get_mut(site), raise);
             }
             idx = next;
-
-            self.path[idx].as_mut().unwrap().operator_type.flip();
-            len += 1;
-            if next == start {
+            let below = self.path[idx].as_ref().unwrap().leg_below(site).unwrap();
+            leg = if above { below } else { below + 2 };
+            if idx == start && leg == start_leg {
                 return len;
             }
         }
     }
-    pub fn diagonal_update(&mut self, beta: f64, j1: f64, rng: &mut ThreadRng) {
+    pub fn diagonal_update<R: Rng>(&mut self, beta: f64, rng: &mut R) {
+        // edges are proposed with probability N_b / N_total, N_b the largest
+        // diagonal weight of the edge, which the acceptance ratios divide out again
+        let weight = self.vertices.total_norm * beta;
         let mut current = self.alpha.clone();
-        let mut last = Bipartite_Id_Vec {
-            even: IdVec::new(),
-            odd: IdVec::new(),
-        };
+        let mut last = Bipartite_Id_Vec::new();
         for _ in 0..self.latice.num_even {
-            last.even.push(OperatorId(usize::MAX));
+            let _ = last.even.push(OperatorId(usize::MAX));
         }
         for _ in 0..self.latice.num_odd {
-            last.odd.push(OperatorId(usize::MAX));
+            let _ = last.odd.push(OperatorId(usize::MAX));
         }
         for idx in 0..self.path.len() {
             let idx = OperatorId(idx);
-            let op = self.path[idx].clone();
-            match op {
-                Some(Operator {
-                    operator_type: OperatorType::OD,
-                    edge,
-                    ..
-                }) => {
-                    current.even[edge.even] ^= true;
-                    current.odd[edge.odd] ^= true;
-                    last.even[edge.even] = idx;
-                    last.odd[edge.odd] = idx;
-                }
-                Some(Operator {
-                    operator_type: OperatorType::D,
-                    edge,
-                    ..
-                }) => {
-                    let p = (self.path.len() - self.n + 1) as f64
-                        / (self.latice.edges.len() as f64
-                            * beta
-                            * 0.5
-                            * match edge.edge_type {
-                                EdgeType::One => j1,
-                                EdgeType::Two => 1.0,
-                            });
+            let op = match self.path[idx].clone() {
+                Some(op) if op.operator_type == OperatorType::D => {
+                    let p = (self.path.len() - self.n + 1) as f64 * self.vertices.norm(&op)
+                        / (weight * self.vertices.weight(&op));
                     if rng.gen::<f64>() < p {
-                        //if 1.0 < p {
                         self.delete(idx);
-                    } else {
-                        last.even[edge.even] = idx;
-                        last.odd[edge.odd] = idx;
+                        continue;
                     }
+                    op
                 }
+                Some(op) => op,
                 None => {
                     //check if insertion is accepted
-                    let edge = self.latice.random_edge(rng);
-                    if current.even[edge.even] == current.odd[edge.odd] {
+                    let op = match self.vertices.random_bond(rng) {
+                        Bond::Edge(id) => {
+                            let edge = self.latice.edges[id];
+                            Operator::diagonal(edge, current.even[edge.even], current.odd[edge.odd])
+                        }
+                        Bond::Plaquette(id) => {
+                            Operator::diagonal_plaquette(self.latice.plaquettes[id], &current)
+                        }
+                    };
+                    let vertex = self.vertices.weight(&op);
+                    if vertex == 0.0 {
                         continue;
                     }
-                    let p = (self.latice.edges.len() as f64
-                        * beta
-                        * 0.5
-                        * match edge.edge_type {
-                            EdgeType::One => j1,
-                            EdgeType::Two => 1.0,
-                        })
-                        / (self.path.len() - self.n) as f64;
-                    if rng.gen::<f64>() < p {
-                        //if 0.0 < p {
-                        self.insert_diag(edge, idx);
-                        last.even[edge.even] = idx;
-                        last.odd[edge.odd] = idx;
+                    let p = weight * vertex
+                        / (self.vertices.norm(&op) * (self.path.len() - self.n) as f64);
+                    if rng.gen::<f64>() >= p {
+                        continue;
                     }
+                    self.insert_diag(op.clone(), idx);
+                    op
                 }
             };
+            for (offset, edge) in op.bonds() {
+                current.even[edge.even] = op.legs[offset + 2];
+                current.odd[edge.odd] = op.legs[offset + 3];
+                last.even[edge.even] = idx;
+                last.odd[edge.odd] = idx;
+            }
         }
         for idx in 0..self.path.len() {
             let idx = OperatorId(idx);
-            if let Some(Operator { edge, .. }) = self.path[idx].clone() {
-                self.path[idx].as_mut().unwrap().even_in_id = last.even[edge.even];
-                self.path[last.odd[edge.odd]].as_mut().unwrap().odd_out_id = idx;
-                last.even[edge.even] = idx;
-                last.odd[edge.odd] = idx;
+            let Some(op) = self.path[idx].clone() else {
+                continue;
+            };
+            for leg in (0..op.num_legs()).filter(|leg| leg & 2 == 0) {
+                let site = op.site(leg);
+                let previous = *last.get(site);
+                self.path[idx].as_mut().unwrap().links[leg] = previous;
+                let previous_op = self.path[previous].as_mut().unwrap();
+                let previous_leg = previous_op.leg_below(site).unwrap() + 2;
+                previous_op.links[previous_leg] = idx;
+                last.set(site, idx);
             }
         }
     }
-    pub fn off_diagonal_update(&mut self, nloop: usize, rng: &mut ThreadRng) -> usize {
+    pub fn off_diagonal_update<R: Rng>(&mut self, nloop: usize, rng: &mut R) -> usize {
         let mut idxs = Vec::new();
+        let mut free = Bipartite_Id_Vec::new();
+        for _ in 0..self.latice.num_even {
+            let _ = free.even.push(true);
+        }
+        for _ in 0..self.latice.num_odd {
+            let _ = free.odd.push(true);
+        }
         for (i, op) in self.path.iter() {
-            if op.is_some() {
+            if let Some(op) = op {
                 idxs.push(i);
+                for (_, edge) in op.bonds() {
+                    free.even[edge.even] = false;
+                    free.odd[edge.odd] = false;
+                }
             }
         }
         let mut count = 0;
-        if idxs.is_empty() {
-            let even_site = Even_Site_Id(rng.gen_range(0..self.alpha.even.len()));
-            self.alpha.even[even_site] ^= true;
-            let odd_site = Odd_Site_Id(rng.gen_range(0..self.alpha.odd.len()));
-            self.alpha.odd[odd_site] ^= true;
-            return 0;
+        if !idxs.is_empty() {
+            for _ in 0..nloop {
+                let idx = idxs[rng.gen_range(0..idxs.len())];
+                let op = self.path[idx].as_ref().unwrap();
+                let leg = rng.gen_range(0..op.num_legs());
+                // a spin 1/2 leg can only go one way, larger spins pick a
+                // direction and bounce straight back if it leaves the levels
+                let raise = if self.vertices.levels == 2 {
+                    op.legs[leg] == 0
+                } else {
+                    rng.gen()
+                };
+                count += self.directed_loop_update(idx, leg, raise, rng);
+            }
+        }
+        //sites without any operator take a uniformly random level
+        let levels = self.vertices.levels as u8;
+        for (site, level) in self.alpha.even.iter_mut() {
+            if free.even[site] {
+                *level = rng.gen_range(0..levels);
+            }
         }
-        for _ in 0..nloop {
-            let idx = idxs[rng.gen_range(0..idxs.len())];
-            count += self.directed_loop_update(idx);
+        for (site, level) in self.alpha.odd.iter_mut() {
+            if free.odd[site] {
+                *level = rng.gen_range(0..levels);
+            }
         }
         count
     }
 
-    pub fn thermalize(&mut self, beta: f64, j1: f64, rng: &mut ThreadRng) -> usize {
-        let mut nloop = 40;
+    pub fn thermalize<R: Rng>(&mut self, beta: f64, rng: &mut R) -> usize {
+        let nloop = 40;
         let mut plato = 0;
         // let mut touched = 0;
         loop {
-            self.diagonal_update(beta, j1, rng);
+            self.diagonal_update(beta, rng);
             self.off_diagonal_update(nloop, rng);
-            while self.n > self.path.len() * 9 / 10 {
-                self.path.push(None);
+            if grow(&mut self.path, self.n) {
                 plato = 0;
             }
             //self.verify();
@@ -273,32 +429,24 @@ impl State {
     pub fn staggered_magnetization(&self) -> f64 {
         let mut sum: f64 = 0.0;
         let mut points = 0.0;
-        let mut current = self.alpha.clone();
-        let mut current_sm: f64 = 0.0;
-        for (_, spin) in &current.even {
-            if *spin {
-                current_sm += 0.5;
-            } else {
-                current_sm -= 0.5;
-            }
-        }
-        for (_, spin) in &current.odd {
-            if *spin {
-                current_sm -= 0.5;
-            } else {
-                current_sm += 0.5;
-            }
-        }
+        let mut current_sm: f64 = self
+            .alpha
+            .even
+            .iter()
+            .map(|(_, &level)| self.model.sz(level))
+            .sum::<f64>()
+            - self
+                .alpha
+                .odd
+                .iter()
+                .map(|(_, &level)| self.model.sz(level))
+                .sum::<f64>();
         for (_, op) in self.path.iter() {
             if let Some(op) = op {
-                if op.operator_type == OperatorType::OD {
-                    if current.even[op.edge.even] {
-                        current_sm -= 2.0;
-                    } else {
-                        current_sm += 2.0;
-                    }
-                    current.even[op.edge.even] ^= true;
-                    current.odd[op.edge.odd] ^= true;
+                for (offset, _) in op.bonds() {
+                    let legs = &op.legs[offset..offset + 4];
+                    current_sm += legs[2] as f64 - legs[0] as f64;
+                    current_sm -= legs[3] as f64 - legs[1] as f64;
                 }
             }
             sum += current_sm.abs();
@@ -306,45 +454,86 @@ impl State {
         }
         sum / points
     }
-    pub fn sample(
+    /// `sum_i S^z_i`, the same in every time slice.
+    pub fn magnetization(&self) -> f64 {
+        let even: f64 = self.alpha.even.iter().map(|(_, &l)| self.model.sz(l)).sum();
+        let odd: f64 = self.alpha.odd.iter().map(|(_, &l)| self.model.sz(l)).sum();
+        even + odd
+    }
+    /// Net displacement of the worldlines along every axis of
+    /// [`Edge::displacement`], `W_a L_a` for the winding numbers `W_a` of a torus.
+    pub fn winding(&self) -> [f64; 3] {
+        let mut winding = [0.0; 3];
+        for (_, op) in self.path.iter() {
+            if let Some(op) = op {
+                for (offset, edge) in op.bonds() {
+                    // S^z, or a particle, moving from the even to the odd site
+                    let hop = op.legs[offset + 3] as f64 - op.legs[offset + 1] as f64;
+                    for (w, d) in winding.iter_mut().zip(edge.displacement) {
+                        *w += hop * d as f64;
+                    }
+                }
+            }
+        }
+        winding
+    }
+    pub fn sample<R: Rng>(
         &mut self,
         // weights: &Vec<f64>,
         nloop: usize,
         beta: f64,
-        j1: f64,
-        rng: &mut ThreadRng,
-    ) -> (f64, f64) {
-        self.diagonal_update(beta, j1, rng);
+        rng: &mut R,
+    ) -> Sample {
+        self.diagonal_update(beta, rng);
         self.off_diagonal_update(nloop, rng);
 
-        let energy = -(self.n as f64) / beta
-            + j1 * self.latice.edge_count_1 as f64 / 4.0
-            + self.latice.edge_count_2 as f64 / 4.0;
-        let sm = self.staggered_magnetization();
-        (energy, sm)
+        Sample {
+            energy: -(self.n as f64) / beta + self.vertices.energy_shift,
+            staggered_magnetization: self.staggered_magnetization(),
+            magnetization: self.magnetization(),
+        }
     }
 
-    pub fn new(latice: &Lattice, m: usize, rng: &mut ThreadRng) -> State {
+    /// Moves the configuration onto a lattice with the same edges but other couplings.
+    pub fn set_lattice(&mut self, latice: &Lattice) {
+        for (_, op) in self.path.iter_mut() {
+            if let Some(op) = op {
+                op.edge = latice.edges[op.edge.id];
+                if let Some(plaquette) = &mut op.plaquette {
+                    *plaquette = latice.plaquettes[plaquette.id];
+                }
+            }
+        }
+        self.latice = latice.clone();
+        self.vertices = Vertices::new(latice, &self.model);
+    }
+
+    /// A Heisenberg model state, see [`State::with_model`].
+    pub fn new<R: Rng>(latice: &Lattice, m: usize, rng: &mut R) -> State {
+        State::with_model(latice, Model::default(), m, rng)
+    }
+
+    /// Random levels and an empty operator string of length `m`.
+    pub fn with_model<R: Rng>(latice: &Lattice, model: Model, m: usize, rng: &mut R) -> State {
+        let levels = model.levels() as u8;
         let mut alpha_even = IdVec::new();
         let mut alpha_odd = IdVec::new();
         for _ in 0..latice.num_even {
-            alpha_even.push(rng.gen());
+            let _ = alpha_even.push(rng.gen_range(0..levels));
         }
         for _ in 0..latice.num_odd {
-            alpha_odd.push(rng.gen());
-        }
-        let mut path = IdVec::new();
-        for _ in 0..m {
-            path.push(None);
+            let _ = alpha_odd.push(rng.gen_range(0..levels));
         }
         let s = State {
             alpha: Bipartite_Id_Vec {
                 even: alpha_even,
                 odd: alpha_odd,
             },
-            path,
+            path: identities(m),
             n: 0,
             latice: latice.clone(),
+            model,
+            vertices: Vertices::new(latice, &model),
         };
         s.verify();
         s
@@ -1,16 +1,28 @@
-use rand::{rngs::ThreadRng, Rng};
+use rand::Rng;
+use std::io;
+use std::path::Path;
 
-pub fn calc_mean(samples: &Vec<f64>) -> f64 {
+/// `n` evenly spaced values starting at `min`, excluding `max`.
+pub fn range(min: f64, max: f64, n: usize) -> Vec<f64> {
+    let mut v = Vec::new();
+    let step = (max - min) / n as f64;
+    for i in 0..n {
+        v.push(min + i as f64 * step);
+    }
+    v
+}
+
+pub fn calc_mean(samples: &[f64]) -> f64 {
     samples.iter().sum::<f64>() / samples.len() as f64
 }
 
-pub fn calc_sd(samples: &Vec<f64>) -> f64 {
+pub fn calc_sd(samples: &[f64]) -> f64 {
     let mean = calc_mean(samples);
     let variance = samples.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / samples.len() as f64;
     variance.sqrt()
 }
 
-pub fn bin(data: &Vec<f64>, bin_size: usize) -> Vec<f64> {
+pub fn bin(data: &[f64], bin_size: usize) -> Vec<f64> {
     let mut binned = Vec::new();
     let mut sum = 0.0;
     for (i, &x) in data.iter().enumerate() {
@@ -20,14 +32,13 @@ pub fn bin(data: &Vec<f64>, bin_size: usize) -> Vec<f64> {
             sum = 0.0;
         }
     }
-    if data.len() % bin_size != 0 {
+    if !data.len().is_multiple_of(bin_size) {
         binned.push(sum / (data.len() % bin_size) as f64);
     }
     binned
 }
 
-pub fn bootstrap(data: &Vec<f64>, n: usize) -> (f64, f64) {
-    let mut rng = rand::thread_rng();
+pub fn bootstrap<R: Rng>(data: &[f64], n: usize, rng: &mut R) -> (f64, f64) {
     let mut means = Vec::new();
     for _ in 0..n {
         let mut sum = 0.0;
@@ -41,15 +52,39 @@ pub fn bootstrap(data: &Vec<f64>, n: usize) -> (f64, f64) {
     (mean, sd)
 }
 
-pub fn write_csv(filename: &str, data: &Vec<Vec<f64>>) {
-    let mut wtr = csv::Writer::from_path(filename).unwrap();
+/// Bootstrap of `<x^2> - <x>^2` from the bins `values` of `x` and `squares`
+/// of `x^2`, which must be taken over the same samples.
+pub fn bootstrap_fluctuation<R: Rng>(
+    values: &[f64],
+    squares: &[f64],
+    n: usize,
+    rng: &mut R,
+) -> (f64, f64) {
+    assert_eq!(values.len(), squares.len(), "One square per value");
+    let mut fluctuations = Vec::new();
+    for _ in 0..n {
+        let (mut sum, mut sum_squares) = (0.0, 0.0);
+        for _ in 0..values.len() {
+            let i = rng.gen_range(0..values.len());
+            sum += values[i];
+            sum_squares += squares[i];
+        }
+        let mean = sum / values.len() as f64;
+        fluctuations.push(sum_squares / values.len() as f64 - mean * mean);
+    }
+    (calc_mean(&fluctuations), calc_sd(&fluctuations))
+}
+
+/// Writes the `data` columns side by side, one row per entry.
+pub fn write_csv<P: AsRef<Path>>(filename: P, data: &[Vec<f64>]) -> io::Result<()> {
+    let mut wtr = csv::Writer::from_path(filename)?;
     //write the vecs in parallel
-    for i in 0..data[0].len() {
+    for i in 0..data.first().map_or(0, Vec::len) {
         let mut record = Vec::new();
-        for j in 0..data.len() {
-            record.push(data[j][i].to_string());
+        for column in data {
+            record.push(column[i].to_string());
         }
-        wtr.write_record(record).unwrap();
+        wtr.write_record(record)?;
     }
-    wtr.flush().unwrap();
+    wtr.flush()
 }
@@ -0,0 +1,244 @@
+use crate::lattice::Lattice;
+use crate::model::Model;
+use crate::rng::{chain_rng, ChainRng};
+use crate::simulation::{Measurements, MonteCarlo};
+use crate::state::{Sample, State};
+use crate::vertex::Vertices;
+use rand::Rng;
+use rayon::prelude::*;
+use std::io;
+
+/// One point of a replica exchange ladder.
+#[derive(Clone, Debug)]
+pub struct Rung {
+    pub lattice: Lattice,
+    pub beta: f64,
+}
+
+/// A configuration together with its own generator; swaps move both.
+struct Replica {
+    state: State,
+    rng: ChainRng,
+}
+
+/// Replica exchange between rungs that differ in `beta` and/or couplings.
+///
+/// Every rung must have the same edges and plaquettes in the same order, only
+/// their couplings may differ. The replicas are updated in parallel and after every
+/// `swap_every` steps neighbouring rungs propose to exchange configurations,
+/// alternating between even and odd pairs, with the Metropolis probability of
+/// [`swap_log_ratio`].
+///
+/// ```no_run
+/// use montecarlo::lattice::make_latice;
+/// use montecarlo::simulation::Observable;
+/// use montecarlo::tempering::Tempering;
+///
+/// let lattices = [1.6, 1.8, 2.0, 2.2].map(|j1| make_latice(8, 8, j1).unwrap());
+/// let mut result = Tempering::in_coupling(lattices.to_vec(), 16.0).run().unwrap();
+/// println!("{:?}", result.acceptance);
+/// let (sm, error) = result.rungs[2].estimate(Observable::StaggeredMagnetization);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Tempering {
+    rungs: Vec<Rung>,
+    model: Model,
+    monte_carlo: MonteCarlo,
+    chain: u64,
+    swap_every: usize,
+}
+
+/// Measurements of every rung and the swap acceptance rate between rung `i`
+/// and `i + 1` in `acceptance[i]`.
+#[derive(Clone, Debug)]
+pub struct TemperingResult {
+    pub rungs: Vec<Measurements>,
+    pub acceptance: Vec<f64>,
+}
+
+/// Logarithm of the Metropolis ratio for exchanging the configurations `a`,
+/// sampled at `beta_a`, and `b`, sampled at `beta_b`.
+///
+/// The SSE weight of a configuration with `n` operators is
+/// `beta^n (M-n)!/M! prod_p W(p)`, so only the expansion orders and the vertex
+/// weights of the operators enter: `(beta_b / beta_a)^(n_a - n_b)` for a beta
+/// ladder and the product of `W' / W` over the operators, `J' / J` without
+/// fields, for a coupling ladder. Each state carries the vertex weights of the
+/// rung it currently sits at.
+pub fn swap_log_ratio(a: &State, beta_a: f64, b: &State, beta_b: f64) -> f64 {
+    let mut log_ratio = (a.n as f64 - b.n as f64) * (beta_b / beta_a).ln();
+    for (from, to) in [(a, b), (b, a)] {
+        for (_, op) in from.path.iter() {
+            if let Some(op) = op {
+                let w_from = from.vertices.weight(op);
+                let w_to = to.vertices.weight(op);
+                if w_from != w_to {
+                    log_ratio += (w_to / w_from).ln();
+                }
+            }
+        }
+    }
+    log_ratio
+}
+
+impl Tempering {
+    pub fn new(rungs: Vec<Rung>) -> Tempering {
+        assert!(!rungs.is_empty(), "Ladder must have at least one rung");
+        for rung in rungs.iter() {
+            assert!(
+                rung.lattice.num_even == rungs[0].lattice.num_even
+                    && rung.lattice.num_odd == rungs[0].lattice.num_odd
+                    && rung.lattice.edges.len() == rungs[0].lattice.edges.len()
+                    && rung
+                        .lattice
+                        .edges
+                        .iter()
+                        .zip(rungs[0].lattice.edges.iter())
+                        .all(|(a, b)| a.even == b.even && a.odd == b.odd)
+                    && rung.lattice.plaquettes.len() == rungs[0].lattice.plaquettes.len()
+                    && rung
+                        .lattice
+                        .plaquettes
+                        .iter()
+                        .zip(rungs[0].lattice.plaquettes.iter())
+                        .all(|(a, b)| a.edges.map(|e| e.id) == b.edges.map(|e| e.id)),
+                "Rungs must share the same edges"
+            );
+        }
+        Tempering {
+            rungs,
+            model: Model::default(),
+            monte_carlo: MonteCarlo::default(),
+            chain: 0,
+            swap_every: 1,
+        }
+    }
+    /// The same lattice at every beta of `betas`.
+    pub fn in_beta(lattice: Lattice, betas: &[f64]) -> Tempering {
+        Tempering::new(
+            betas
+                .iter()
+                .map(|&beta| Rung {
+                    lattice: lattice.clone(),
+                    beta,
+                })
+                .collect(),
+        )
+    }
+    /// Every lattice of `lattices`, which differ only in their couplings, at `beta`.
+    pub fn in_coupling(lattices: Vec<Lattice>, beta: f64) -> Tempering {
+        Tempering::new(
+            lattices
+                .into_iter()
+                .map(|lattice| Rung { lattice, beta })
+                .collect(),
+        )
+    }
+    /// Model of every rung, the ladder only changes `beta` and the couplings.
+    pub fn model(mut self, model: Model) -> Tempering {
+        self.model = model;
+        self
+    }
+    pub fn monte_carlo(mut self, monte_carlo: MonteCarlo) -> Tempering {
+        self.monte_carlo = monte_carlo;
+        self
+    }
+    /// Replica `i` uses stream `chain + i * 2^32`, the swaps use `chain + 2^63`.
+    pub fn chain(mut self, chain: u64) -> Tempering {
+        self.chain = chain;
+        self
+    }
+    /// Sampling steps between two rounds of swap proposals.
+    pub fn swap_every(mut self, swap_every: usize) -> Tempering {
+        assert!(swap_every > 0, "swap_every must be positive");
+        self.swap_every = swap_every;
+        self
+    }
+    pub fn rungs(&self) -> &[Rung] {
+        &self.rungs
+    }
+
+    /// Thermalizes every replica at its own rung, then takes
+    /// `monte_carlo.samples` measurements per rung while exchanging replicas.
+    pub fn run(&self) -> io::Result<TemperingResult> {
+        let mc = &self.monte_carlo;
+        for rung in &self.rungs {
+            Vertices::check(&rung.lattice, &self.model)?;
+        }
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(mc.threads)
+            .build()
+            .map_err(io::Error::other)?;
+        let mut replicas: Vec<Replica> = pool.install(|| {
+            self.rungs
+                .par_iter()
+                .enumerate()
+                .map(|(i, rung)| {
+                    let mut rng = chain_rng(mc.seed, self.chain + ((i as u64) << 32));
+                    let mut state = State::with_model(&rung.lattice, self.model, 10, &mut rng);
+                    state.thermalize(rung.beta, &mut rng);
+                    Replica { state, rng }
+                })
+                .collect()
+        });
+        let mut swap_rng = chain_rng(mc.seed, self.chain + (1 << 63));
+        let mut rungs: Vec<Measurements> = replicas
+            .iter()
+            .map(|replica| Measurements::new(mc.clone(), replica.rng.clone()))
+            .collect();
+        let mut proposed = vec![0usize; self.rungs.len().saturating_sub(1)];
+        let mut accepted = vec![0usize; self.rungs.len().saturating_sub(1)];
+
+        for step in 0..mc.samples {
+            let samples: Vec<Sample> = pool.install(|| {
+                replicas
+                    .par_iter_mut()
+                    .zip(self.rungs.par_iter())
+                    .map(|(replica, rung)| {
+                        let sample = replica.state.sample(mc.nloop, rung.beta, &mut replica.rng);
+                        // a replica arriving from a smaller beta may need a longer operator string
+                        while replica.state.n > replica.state.path.len() * 9 / 10 {
+                            let _ = replica.state.path.push(None);
+                        }
+                        sample
+                    })
+                    .collect()
+            });
+            for (measurements, sample) in rungs.iter_mut().zip(samples.iter()) {
+                measurements.push(sample);
+            }
+            if (step + 1) % self.swap_every != 0 {
+                continue;
+            }
+            let round = (step + 1) / self.swap_every;
+            for i in (round % 2..proposed.len()).step_by(2) {
+                let log_ratio = swap_log_ratio(
+                    &replicas[i].state,
+                    self.rungs[i].beta,
+                    &replicas[i + 1].state,
+                    self.rungs[i + 1].beta,
+                );
+                proposed[i] += 1;
+                if swap_rng.gen::<f64>().ln() < log_ratio {
+                    accepted[i] += 1;
+                    replicas.swap(i, i + 1);
+                    replicas[i].state.set_lattice(&self.rungs[i].lattice);
+                    replicas[i + 1]
+                        .state
+                        .set_lattice(&self.rungs[i + 1].lattice);
+                }
+            }
+        }
+
+        let acceptance = accepted
+            .iter()
+            .zip(proposed.iter())
+            .map(|(&a, &p)| if p == 0 { 0.0 } else { a as f64 / p as f64 })
+            .collect();
+        // the bootstrap of every rung continues the generator of its final replica
+        for (measurements, replica) in rungs.iter_mut().zip(replicas) {
+            measurements.rng = replica.rng;
+        }
+        Ok(TemperingResult { rungs, acceptance })
+    }
+}
@@ -0,0 +1,326 @@
+//! SSE of the transverse-field Ising model with the cluster update of
+//! Sandvik, Phys. Rev. E 68, 056701 (2003).
+//!
+//! The operator string holds three kinds of operators: the constant `h/2` and
+//! the flip `h S^x` on a site, and the Ising bond operator `|J_b|/4 - J_b S^z_i S^z_j`,
+//! which is nonzero only on the two spin states its coupling favours. A bond
+//! operator ties its four legs into one cluster while site operators cut the
+//! clusters in imaginary time, so flipping a cluster turns the constants and
+//! flips at its ends into each other.
+
+use crate::lattice::{Bipartite_Id_Vec, Edge, Even_Site_Id, Lattice, Odd_Site_Id, Site_Id};
+use crate::state::{grow, identities, OperatorId, OperatorString, Sample};
+use rand::distributions::WeightedIndex;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+/// `H = sum_b J_b S^z_i S^z_j - field sum_i S^x_i` on the bonds of a lattice.
+///
+/// Positive couplings are antiferromagnetic as in [`crate::model::Model`], so
+/// the staggered magnetization is the order parameter on the usual lattices.
+/// In one dimension the quantum critical point is at `field = J / 2`.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TransverseIsing {
+    pub field: f64,
+}
+
+impl TransverseIsing {
+    pub fn new(field: f64) -> TransverseIsing {
+        assert!(field >= 0.0, "field must not be negative");
+        TransverseIsing { field }
+    }
+
+    /// Weight of an Ising operator on `edge` between spins `even` and `odd`.
+    pub fn bond_weight(edge: &Edge, even: bool, odd: bool) -> f64 {
+        if (even != odd) == (edge.coupling > 0.0) {
+            edge.coupling.abs() / 2.0
+        } else {
+            0.0
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum IsingOperator {
+    /// The constant `field / 2` on a site
+    Constant(Site_Id),
+    /// `field S^x` on a site
+    Flip(Site_Id),
+    Bond(Edge),
+}
+
+#[derive(Clone, Debug)]
+pub struct IsingState {
+    pub alpha: Bipartite_Id_Vec<bool>,
+    pub path: OperatorString<IsingOperator>,
+    pub n: usize,
+    pub latice: Lattice,
+    pub model: TransverseIsing,
+    /// Sum of the weights of every diagonal operator that can be proposed.
+    pub total_norm: f64,
+    /// Sites, even ones first, followed by the edges.
+    distribution: WeightedIndex<f64>,
+}
+
+impl IsingState {
+    /// Random spins and an empty operator string of length `m`.
+    pub fn new<R: Rng>(
+        latice: &Lattice,
+        model: TransverseIsing,
+        m: usize,
+        rng: &mut R,
+    ) -> IsingState {
+        let mut alpha = Bipartite_Id_Vec::new();
+        for _ in 0..latice.num_even {
+            let _ = alpha.even.push(rng.gen());
+        }
+        for _ in 0..latice.num_odd {
+            let _ = alpha.odd.push(rng.gen());
+        }
+        let num_sites = latice.num_even + latice.num_odd;
+        let norms: Vec<f64> = std::iter::repeat_n(model.field / 2.0, num_sites)
+            .chain(latice.edges.iter().map(|e| e.coupling.abs() / 2.0))
+            .collect();
+        IsingState {
+            alpha,
+            path: identities(m),
+            n: 0,
+            latice: latice.clone(),
+            model,
+            total_norm: norms.iter().sum(),
+            distribution: WeightedIndex::new(norms).unwrap(),
+        }
+    }
+
+    fn site(&self, i: usize) -> Site_Id {
+        if i < self.latice.num_even {
+            Site_Id::Even(Even_Site_Id(i))
+        } else {
+            Site_Id::Odd(Odd_Site_Id(i - self.latice.num_even))
+        }
+    }
+
+    /// `sum_b |J_b| / 4 + N field / 2`, added back to the energy estimator.
+    pub fn energy_shift(&self) -> f64 {
+        let num_sites = self.latice.num_even + self.latice.num_odd;
+        self.latice
+            .edges
+            .iter()
+            .map(|e| e.coupling.abs() / 4.0)
+            .sum::<f64>()
+            + num_sites as f64 * self.model.field / 2.0
+    }
+
+    pub fn verify(&self) {
+        //check that every bond operator acts on spins it favours and alpha loops
+        let mut current = self.alpha.clone();
+        let mut n = 0;
+        for (i, op) in self.path.iter() {
+            match op {
+                Some(IsingOperator::Bond(edge)) => {
+                    let (even, odd) = (current.even[edge.even], current.odd[edge.odd]);
+                    assert!(
+                        TransverseIsing::bond_weight(edge, even, odd) > 0.0,
+                        "op {:?} has zero weight",
+                        i
+                    );
+                }
+                Some(IsingOperator::Flip(site)) => *current.get_mut(*site) ^= true,
+                Some(IsingOperator::Constant(_)) | None => {}
+            }
+            n += op.is_some() as usize;
+        }
+        assert_eq!(current, self.alpha, "alpha not a loop");
+        assert_eq!(n, self.n, "n count error");
+    }
+
+    pub fn diagonal_update<R: Rng>(&mut self, beta: f64, rng: &mut R) {
+        // operators are proposed with probability W / total_norm, so every
+        // nonzero one is accepted with the same ratio
+        let weight = self.total_norm * beta;
+        let mut current = self.alpha.clone();
+        for idx in 0..self.path.len() {
+            let idx = OperatorId(idx);
+            match self.path[idx] {
+                Some(IsingOperator::Flip(site)) => *current.get_mut(site) ^= true,
+                Some(_) => {
+                    let p = (self.path.len() - self.n + 1) as f64 / weight;
+                    if rng.gen::<f64>() < p {
+                        self.path[idx] = None;
+                        self.n -= 1;
+                    }
+                }
+                None => {
+                    let i = rng.sample(&self.distribution);
+                    let num_sites = self.latice.num_even + self.latice.num_odd;
+                    let op = if i < num_sites {
+                        IsingOperator::Constant(self.site(i))
+                    } else {
+                        let edge = self.latice.edges[i - num_sites];
+                        let (even, odd) = (current.even[edge.even], current.odd[edge.odd]);
+                        if TransverseIsing::bond_weight(&edge, even, odd) == 0.0 {
+                            continue;
+                        }
+                        IsingOperator::Bond(edge)
+                    };
+                    let p = weight / (self.path.len() - self.n) as f64;
+                    if rng.gen::<f64>() < p {
+                        self.path[idx] = Some(op);
+                        self.n += 1;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Builds every cluster and flips each with probability 1/2, returning
+    /// the number of clusters.
+    pub fn cluster_update<R: Rng>(&mut self, rng: &mut R) -> usize {
+        // leg 4 p + l of operator p, numbered as the vertices of crate::vertex;
+        // site operators only use legs 0 (below) and 2 (above)
+        let mut parent: Vec<usize> = (0..4 * self.path.len()).collect();
+        fn find(parent: &mut [usize], mut leg: usize) -> usize {
+            while parent[leg] != leg {
+                parent[leg] = parent[parent[leg]];
+                leg = parent[leg];
+            }
+            leg
+        }
+        fn union(parent: &mut [usize], a: usize, b: usize) {
+            let (a, b) = (find(parent, a), find(parent, b));
+            parent[a] = b;
+        }
+        let mut first = Bipartite_Id_Vec::new();
+        for _ in 0..self.latice.num_even {
+            let _ = first.even.push(usize::MAX);
+        }
+        for _ in 0..self.latice.num_odd {
+            let _ = first.odd.push(usize::MAX);
+        }
+        let mut last = first.clone();
+        let mut connect = |parent: &mut [usize], site: Site_Id, below: usize, above: usize| {
+            match *last.get(site) {
+                usize::MAX => first.set(site, below),
+                previous => union(parent, previous, below),
+            }
+            last.set(site, above);
+        };
+        for (idx, op) in self.path.iter() {
+            let p = 4 * idx.0;
+            match op {
+                Some(IsingOperator::Bond(edge)) => {
+                    for leg in 1..4 {
+                        union(&mut parent, p, p + leg);
+                    }
+                    connect(&mut parent, Site_Id::Even(edge.even), p, p + 2);
+                    connect(&mut parent, Site_Id::Odd(edge.odd), p + 1, p + 3);
+                }
+                Some(IsingOperator::Constant(site) | IsingOperator::Flip(site)) => {
+                    connect(&mut parent, *site, p, p + 2);
+                }
+                None => {}
+            }
+        }
+        // close every site around the periodic imaginary time
+        for (site, &below) in first.even.iter() {
+            if below != usize::MAX {
+                union(&mut parent, last.even[site], below);
+            }
+        }
+        for (site, &below) in first.odd.iter() {
+            if below != usize::MAX {
+                union(&mut parent, last.odd[site], below);
+            }
+        }
+
+        let mut flips: Vec<Option<bool>> = vec![None; parent.len()];
+        let mut clusters = 0;
+        let mut flipped = |parent: &mut [usize], leg: usize, rng: &mut R| {
+            let root = find(parent, leg);
+            *flips[root].get_or_insert_with(|| {
+                clusters += 1;
+                rng.gen()
+            })
+        };
+        for (idx, op) in self.path.iter_mut() {
+            if let Some(IsingOperator::Constant(site) | IsingOperator::Flip(site)) = *op {
+                let p = 4 * idx.0;
+                if flipped(&mut parent, p, rng) != flipped(&mut parent, p + 2, rng) {
+                    *op = Some(match *op {
+                        Some(IsingOperator::Constant(_)) => IsingOperator::Flip(site),
+                        _ => IsingOperator::Constant(site),
+                    });
+                }
+            }
+        }
+        //spins without any operator are flipped with probability 1/2
+        for (site, spin) in self.alpha.even.iter_mut() {
+            let flip = match first.even[site] {
+                usize::MAX => rng.gen(),
+                below => flipped(&mut parent, below, rng),
+            };
+            *spin ^= flip;
+        }
+        for (site, spin) in self.alpha.odd.iter_mut() {
+            let flip = match first.odd[site] {
+                usize::MAX => rng.gen(),
+                below => flipped(&mut parent, below, rng),
+            };
+            *spin ^= flip;
+        }
+        clusters
+    }
+
+    /// Equilibrates like [`crate::state::State::thermalize`], growing the
+    /// operator string until it stays above `n / 0.9`.
+    pub fn thermalize<R: Rng>(&mut self, beta: f64, rng: &mut R) {
+        let mut plato = 0;
+        while plato < 5000 {
+            self.diagonal_update(beta, rng);
+            self.cluster_update(rng);
+            plato += 1;
+            if grow(&mut self.path, self.n) {
+                plato = 0;
+            }
+        }
+    }
+
+    /// Time averages of `|sum_i (-1)^i S^z_i|` and `sum_i S^z_i` over every
+    /// slice of the operator string.
+    fn magnetizations(&self) -> (f64, f64) {
+        let sz = |up: bool| if up { 0.5 } else { -0.5 };
+        let mut current = self.alpha.clone();
+        let mut m: f64 = current.even.iter().map(|(_, &s)| sz(s)).sum::<f64>()
+            + current.odd.iter().map(|(_, &s)| sz(s)).sum::<f64>();
+        let mut ms: f64 = current.even.iter().map(|(_, &s)| sz(s)).sum::<f64>()
+            - current.odd.iter().map(|(_, &s)| sz(s)).sum::<f64>();
+        let (mut sum_m, mut sum_ms) = (0.0, 0.0);
+        for (_, op) in self.path.iter() {
+            if let Some(IsingOperator::Flip(site)) = op {
+                let up = *current.get(*site);
+                m -= 2.0 * sz(up);
+                ms -= match site {
+                    Site_Id::Even(_) => 2.0 * sz(up),
+                    Site_Id::Odd(_) => -2.0 * sz(up),
+                };
+                *current.get_mut(*site) ^= true;
+            }
+            sum_m += m;
+            sum_ms += ms.abs();
+        }
+        let slices = self.path.len() as f64;
+        (sum_ms / slices, sum_m / slices)
+    }
+
+    pub fn sample<R: Rng>(&mut self, beta: f64, rng: &mut R) -> Sample {
+        self.diagonal_update(beta, rng);
+        self.cluster_update(rng);
+        let (staggered_magnetization, magnetization) = self.magnetizations();
+        Sample {
+            energy: -(self.n as f64) / beta + self.energy_shift(),
+            staggered_magnetization,
+            magnetization,
+        }
+    }
+}
@@ -0,0 +1,309 @@
+//! Vertex weights and directed-loop exit probabilities of bond operators.
+//!
+//! The four legs of a vertex are numbered
+//!
+//! ```text
+//!   2 (even, above)   3 (odd, above)
+//!   0 (even, below)   1 (odd, below)
+//! ```
+//!
+//! so `leg ^ 1` is the other site at the same time and `leg ^ 2` the same site
+//! on the other side of the operator. Every leg carries the level `S^z + S`
+//! of its site, so for spin 1/2 a vertex state has bit `leg` set when the
+//! spin on that leg is up.
+//!
+//! A plaquette operator is a product of two such vertices, its second bond
+//! has legs 4 to 7. Each factor is a singlet projector, whose table is the
+//! spin 1/2 Heisenberg one, so a loop entering a plaquette leaves it on the same bond.
+
+use crate::lattice::{Bipartite_Id_Vec, Lattice};
+use crate::model::Model;
+use crate::state::Operator;
+use rand::distributions::WeightedIndex;
+use rand::Rng;
+use std::collections::HashMap;
+use std::io;
+
+/// Index of the vertex with the levels `legs[0..4]` on its legs, leg `l`
+/// being digit `l` in base `levels`.
+pub fn vertex_state(legs: &[u8], levels: usize) -> usize {
+    legs[..4]
+        .iter()
+        .rev()
+        .fold(0, |state, &level| state * levels + level as usize)
+}
+
+/// The levels on the legs of vertex `state`, see [`vertex_state`].
+pub fn vertex_legs(state: usize, levels: usize) -> [u8; 4] {
+    [0, 1, 2, 3].map(|leg| (state / levels.pow(leg) % levels) as u8)
+}
+
+/// Exit probabilities of the directed loop on one kind of bond.
+#[derive(Clone, Debug)]
+pub struct VertexTable {
+    /// Number of S^z states of a site.
+    pub levels: usize,
+    /// Weight of every vertex, indexed by [`vertex_state`].
+    pub weights: Vec<f64>,
+    /// The largest diagonal weight, bonds are proposed in proportion to it.
+    pub norm: f64,
+    /// `exits[2 * (4 * state + entrance) + raise][exit]`, cumulative over `exit`.
+    exits: Vec<[f64; 4]>,
+}
+
+impl VertexTable {
+    pub fn new(weights: Vec<f64>, levels: usize) -> VertexTable {
+        assert_eq!(weights.len(), levels.pow(4), "one weight per vertex state");
+        let mut exits = vec![[0.0; 4]; 8 * weights.len()];
+        for (state, &weight) in weights.iter().enumerate() {
+            if weight <= 0.0 {
+                continue;
+            }
+            let legs = vertex_legs(state, levels);
+            for entrance in 0..4 {
+                for raise in [false, true] {
+                    // the loop changes the entrance by one level and the exit
+                    // so that S^z is conserved: the opposite way on the same
+                    // side of the operator, the same way on the other side;
+                    // the states reachable this way form one set of directed
+                    // loop equations, in which exiting on the entrance bounces
+                    let change = if raise { 1 } else { -1 };
+                    let group_weights: Vec<f64> = (0..4)
+                        .map(|exit| {
+                            let mut legs = legs.map(|level| level as i32);
+                            legs[entrance] += change;
+                            legs[exit] += if exit & 2 == entrance & 2 {
+                                -change
+                            } else {
+                                change
+                            };
+                            if legs
+                                .iter()
+                                .all(|&level| (0..levels as i32).contains(&level))
+                            {
+                                let legs = legs.map(|level| level as u8);
+                                weights[vertex_state(&legs, levels)]
+                            } else {
+                                0.0
+                            }
+                        })
+                        .collect();
+                    let a = solve_directed_loop(&group_weights);
+                    let row = &mut exits[2 * (4 * state + entrance) + raise as usize];
+                    let mut cumulative = 0.0;
+                    for exit in 0..4 {
+                        cumulative += a[entrance * 4 + exit] / weight;
+                        row[exit] = cumulative;
+                    }
+                    row[3] = 1.0;
+                }
+            }
+        }
+        let norm = (0..levels.pow(2))
+            .map(|below| weights[below + levels.pow(2) * below])
+            .fold(0.0, f64::max);
+        VertexTable {
+            levels,
+            weights,
+            norm,
+            exits,
+        }
+    }
+
+    /// The exit leg for a loop entering `state` on `entrance`, raising
+    /// (`raise`) or lowering its level, given a uniform `r` in [0, 1).
+    ///
+    /// The exit is raised when it lies on the other side of the operator than
+    /// the entrance, and lowered otherwise.
+    pub fn exit(&self, state: usize, entrance: usize, raise: bool, r: f64) -> usize {
+        let exits = &self.exits[2 * (4 * state + entrance) + raise as usize];
+        exits.iter().position(|&p| r < p).unwrap_or(3)
+    }
+}
+
+/// Symmetric non-negative `a` with row sums `weights` and the least weight
+/// on the diagonal (the bounces).
+///
+/// Only the largest weight bounces, and only by how much it exceeds the sum of
+/// the others. The remaining weight is paired off by repeatedly spreading the
+/// largest row over the others, lowering the largest of them first.
+pub fn solve_directed_loop(weights: &[f64]) -> Vec<f64> {
+    let n = weights.len();
+    let mut a = vec![0.0; n * n];
+    let mut rest = weights.to_vec();
+    let total: f64 = rest.iter().sum();
+    let largest = (0..n).fold(0, |a, i| if rest[i] > rest[a] { i } else { a });
+    let excess = 2.0 * rest[largest] - total;
+    if excess > 0.0 {
+        a[largest * n + largest] = excess;
+        rest[largest] -= excess;
+    }
+    let mut open: Vec<usize> = (0..n).collect();
+    while open.len() > 1 {
+        open.sort_by(|&i, &j| rest[j].total_cmp(&rest[i]));
+        let i = open.remove(0);
+        // water filling: lower the others to `level` so they absorb rest[i]
+        let mut level = 0.0;
+        let mut remaining = rest[i];
+        for (k, &j) in open.iter().enumerate() {
+            let next = open.get(k + 1).map_or(0.0, |&l| rest[l]);
+            let capacity = (rest[j] - next) * (k + 1) as f64;
+            if capacity >= remaining {
+                level = rest[j] - remaining / (k + 1) as f64;
+                break;
+            }
+            remaining -= capacity;
+        }
+        for &j in open.iter() {
+            let t = (rest[j] - level).max(0.0);
+            a[i * n + j] += t;
+            a[j * n + i] += t;
+            rest[j] -= t;
+        }
+        rest[i] = 0.0;
+    }
+    a
+}
+
+/// What the diagonal update proposes to insert, by its id in the lattice.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Bond {
+    Edge(usize),
+    Plaquette(usize),
+}
+
+/// Vertex tables of every edge of a lattice, shared between equal edges.
+#[derive(Clone, Debug)]
+pub struct Vertices {
+    /// Number of S^z states of a site.
+    pub levels: usize,
+    tables: Vec<VertexTable>,
+    edge_tables: Vec<usize>,
+    /// `P = 1/4 - S_i . S_j`, both factors of every plaquette operator.
+    projector: VertexTable,
+    plaquette_couplings: Vec<f64>,
+    /// Sum of the norms of all edges and plaquettes.
+    pub total_norm: f64,
+    distribution: WeightedIndex<f64>,
+    /// `sum_b C_b`, added back to the energy estimator.
+    pub energy_shift: f64,
+}
+
+impl Vertices {
+    /// Whether `model` can be sampled on `lattice`, an `InvalidInput` error
+    /// for plaquettes with spin above 1/2 or a sign problem.
+    pub fn check(lattice: &Lattice, model: &Model) -> io::Result<()> {
+        if !lattice.plaquettes.is_empty() && model.levels() != 2 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "plaquette operators need spin 1/2",
+            ));
+        }
+        if let Some(problem) = model.sign_problem(lattice) {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, problem));
+        }
+        Ok(())
+    }
+
+    /// Panics where [`Vertices::check`] fails.
+    pub fn new(lattice: &Lattice, model: &Model) -> Vertices {
+        assert!(
+            lattice.plaquettes.is_empty() || model.levels() == 2,
+            "Plaquette operators need spin 1/2"
+        );
+        if let Some(problem) = model.sign_problem(lattice) {
+            panic!("The couplings of the lattice have a {}", problem);
+        }
+        let mut coordination = Bipartite_Id_Vec::new();
+        for _ in 0..lattice.num_even {
+            let _ = coordination.even.push(0);
+        }
+        for _ in 0..lattice.num_odd {
+            let _ = coordination.odd.push(0);
+        }
+        for edge in lattice.edges.iter() {
+            coordination.even[edge.even] += 1;
+            coordination.odd[edge.odd] += 1;
+        }
+        let mut tables = Vec::new();
+        let mut index = HashMap::new();
+        let mut edge_tables = Vec::new();
+        let mut energy_shift = 0.0;
+        for edge in lattice.edges.iter() {
+            let (weights, shift) = model.bond_weights(
+                edge.coupling,
+                1.0 / coordination.even[edge.even] as f64,
+                1.0 / coordination.odd[edge.odd] as f64,
+            );
+            energy_shift += shift;
+            let key: Vec<u64> = weights.iter().map(|w| w.to_bits()).collect();
+            let table = *index.entry(key).or_insert_with(|| {
+                tables.push(VertexTable::new(weights, model.levels()));
+                tables.len() - 1
+            });
+            edge_tables.push(table);
+        }
+        let projector = VertexTable::new(Model::heisenberg().bond_weights(1.0, 0.0, 0.0).0, 2);
+        let plaquette_couplings: Vec<f64> = lattice.plaquettes.iter().map(|p| p.coupling).collect();
+        let norms: Vec<f64> = edge_tables
+            .iter()
+            .map(|&t| tables[t].norm)
+            .chain(
+                plaquette_couplings
+                    .iter()
+                    .map(|q| q * projector.norm * projector.norm),
+            )
+            .collect();
+        Vertices {
+            levels: model.levels(),
+            total_norm: norms.iter().sum(),
+            distribution: WeightedIndex::new(norms).unwrap(),
+            tables,
+            edge_tables,
+            projector,
+            plaquette_couplings,
+            energy_shift,
+        }
+    }
+    pub fn table(&self, edge: usize) -> &VertexTable {
+        &self.tables[self.edge_tables[edge]]
+    }
+    /// Weight of the vertex of `op` in its current state.
+    pub fn weight(&self, op: &Operator) -> f64 {
+        match op.plaquette {
+            Some(plaquette) => {
+                self.plaquette_couplings[plaquette.id]
+                    * self.projector.weights[vertex_state(&op.legs[..4], 2)]
+                    * self.projector.weights[vertex_state(&op.legs[4..], 2)]
+            }
+            None => self.table(op.edge.id).weights[vertex_state(&op.legs, self.levels)],
+        }
+    }
+    /// The largest diagonal weight of the bond or plaquette of `op`.
+    pub fn norm(&self, op: &Operator) -> f64 {
+        match op.plaquette {
+            Some(plaquette) => {
+                self.plaquette_couplings[plaquette.id] * self.projector.norm * self.projector.norm
+            }
+            None => self.table(op.edge.id).norm,
+        }
+    }
+    /// The exit leg for a loop entering `op` on `entrance`, see [`VertexTable::exit`].
+    pub fn exit(&self, op: &Operator, entrance: usize, raise: bool, r: f64) -> usize {
+        let offset = entrance & 4;
+        let table = match op.plaquette {
+            Some(_) => &self.projector,
+            None => self.table(op.edge.id),
+        };
+        let state = vertex_state(&op.legs[offset..], table.levels);
+        offset + table.exit(state, entrance & 3, raise, r)
+    }
+    /// Picks an edge or plaquette with probability proportional to its norm.
+    pub fn random_bond<R: Rng>(&self, rng: &mut R) -> Bond {
+        let i = rng.sample(&self.distribution);
+        match i.checked_sub(self.edge_tables.len()) {
+            Some(plaquette) => Bond::Plaquette(plaquette),
+            None => Bond::Edge(i),
+        }
+    }
+}
@@ -0,0 +1,220 @@
+use montecarlo::boson::BoseHubbard;
+use montecarlo::ed::ExactDiagonalization;
+use montecarlo::geometry::{chain, square};
+use montecarlo::lattice::{make_jq_latice, make_latice, Boundary, Lattice, LatticeConstructor};
+use montecarlo::simulation::MonteCarlo;
+use std::io;
+
+fn monte_carlo(seed: u64) -> MonteCarlo {
+    MonteCarlo {
+        samples: 20000,
+        nloop: 10,
+        bin_size: 100,
+        bootstraps: 200,
+        seed,
+        ..MonteCarlo::default()
+    }
+}
+
+fn ring(length: usize) -> Lattice {
+    let mut constructor = LatticeConstructor::new((0..length).collect());
+    for i in 0..length {
+        constructor.add_displaced_edge(i, (i + 1) % length, 1.0, [1, 0, 0]);
+    }
+    constructor.build().unwrap()
+}
+
+#[test]
+fn bosons_match_exact_diagonalization() {
+    let cases = [
+        (
+            make_latice(4, 2, 1.0).unwrap(),
+            BoseHubbard::hard_core(1.0, 0.5, 0.3).unwrap(),
+        ),
+        (
+            make_latice(4, 2, 0.6).unwrap(),
+            BoseHubbard::hard_core(0.8, 2.0, 1.5).unwrap(),
+        ),
+        (
+            ring(6),
+            BoseHubbard::hard_core(1.0, 0.0, 1.0)
+                .unwrap()
+                .soft_core(3.0, 2)
+                .unwrap(),
+        ),
+        (
+            ring(4),
+            BoseHubbard::hard_core(0.5, 0.4, 2.0)
+                .unwrap()
+                .soft_core(1.5, 3)
+                .unwrap(),
+        ),
+    ];
+    for (seed, (lattice, bosons)) in cases.into_iter().enumerate() {
+        let sites = (lattice.num_even + lattice.num_odd) as f64;
+        for beta in [1.0, 4.0] {
+            let exact = ExactDiagonalization::with_model(
+                &bosons.lattice(&lattice).unwrap(),
+                &bosons.model(),
+            )
+            .thermodynamics(beta);
+            let estimates = bosons
+                .run(&lattice, beta, &monte_carlo(seed as u64))
+                .unwrap();
+            for (name, (mean, error), exact) in [
+                ("energy", estimates.energy, exact.energy),
+                ("density", estimates.density, exact.magnetization / sites),
+                (
+                    "compressibility",
+                    estimates.compressibility,
+                    exact.uniform_susceptibility / sites,
+                ),
+            ] {
+                assert!(
+                    (mean - exact).abs() < 4.0 * error + 1e-3,
+                    "{} {:?} beta={}: sse {} +- {} but exact {}",
+                    name,
+                    bosons,
+                    beta,
+                    mean,
+                    error,
+                    exact
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn stiffness_separates_superfluid_and_mott_insulator() {
+    let lattice = make_latice(4, 4, 1.0).unwrap();
+    let superfluid = BoseHubbard::hard_core(1.0, 0.0, 0.0)
+        .unwrap()
+        .run(&lattice, 4.0, &monte_carlo(1))
+        .unwrap();
+    let (stiffness, error) = superfluid.stiffness;
+    assert!(
+        stiffness > 0.2 && stiffness > 10.0 * error,
+        "{:?}",
+        superfluid
+    );
+
+    // one boson per site, deep inside the first Mott lobe
+    let mott = BoseHubbard::hard_core(0.1, 0.0, 5.0)
+        .unwrap()
+        .soft_core(10.0, 2)
+        .unwrap()
+        .run(&lattice, 4.0, &monte_carlo(2))
+        .unwrap();
+    assert!((mott.density.0 - 1.0).abs() < 0.01, "{:?}", mott);
+    assert!(mott.stiffness.0 < 0.02, "{:?}", mott);
+
+    // without a geometry there is nothing to wind around
+    let unplaced = BoseHubbard::hard_core(1.0, 0.0, 0.0)
+        .unwrap()
+        .run(
+            &{
+                let mut constructor = LatticeConstructor::new((0..4).collect());
+                for i in 0..4 {
+                    constructor.add_edge(i, (i + 1) % 4, 1.0);
+                }
+                constructor.build().unwrap()
+            },
+            2.0,
+            &monte_carlo(3),
+        )
+        .unwrap();
+    assert_eq!(unplaced.stiffness.0, 0.0);
+    // nor along the open directions of a lattice
+    let open = square(4, 4, [Boundary::Open; 2]).unwrap().build().unwrap();
+    let open = BoseHubbard::hard_core(1.0, 0.0, 0.0)
+        .unwrap()
+        .run(&open, 2.0, &monte_carlo(4))
+        .unwrap();
+    assert_eq!(open.stiffness.0, 0.0);
+}
+
+/// `ln Z` of free hard-core bosons with hopping `t` on a ring of `length`
+/// sites, where every hop along the ring picks up the phase `twist`. By the
+/// Jordan-Wigner transformation these are free fermions whose momenta are
+/// shifted by half a quantum for an even number of particles.
+fn free_ring_log_z(length: usize, t: f64, mu: f64, beta: f64, twist: f64) -> f64 {
+    let mut z = 0.0;
+    for parity in 0..2 {
+        let shift = if parity == 0 {
+            std::f64::consts::PI
+        } else {
+            0.0
+        };
+        // elementary symmetric polynomials of the one-particle weights
+        let mut e = vec![0.0; length + 1];
+        e[0] = 1.0;
+        for m in 0..length {
+            let k = (std::f64::consts::TAU * m as f64 + shift) / length as f64;
+            let weight = (beta * (2.0 * t * (k + twist).cos() + mu)).exp();
+            for count in (1..=length).rev() {
+                e[count] += e[count - 1] * weight;
+            }
+        }
+        z += (0..=length)
+            .filter(|count| count % 2 == parity)
+            .map(|count| e[count])
+            .sum::<f64>();
+    }
+    z.ln()
+}
+
+#[test]
+fn stiffness_of_free_bosons_on_a_ring() {
+    let lattice = chain(6, Boundary::Periodic).unwrap().build().unwrap();
+    for (seed, (mu, beta)) in [(0.0, 1.0), (0.0, 4.0), (0.7, 2.0)].into_iter().enumerate() {
+        // rho_s = F''(0) / sites for the free energy F of the twisted ring
+        let h = 1e-3;
+        let log_z = |twist| free_ring_log_z(6, 1.0, mu, beta, twist);
+        let curvature = (log_z(h) - 2.0 * log_z(0.0) + log_z(-h)) / (h * h);
+        let exact = -curvature / (beta * 6.0);
+        let monte_carlo = MonteCarlo {
+            chains: 2,
+            threads: 2,
+            ..monte_carlo(10 + seed as u64)
+        };
+        let estimates = BoseHubbard::hard_core(1.0, 0.0, mu)
+            .unwrap()
+            .run(&lattice, beta, &monte_carlo)
+            .unwrap();
+        let (stiffness, error) = estimates.stiffness;
+        assert!(
+            (stiffness - exact).abs() < 4.0 * error + 1e-3,
+            "mu={} beta={}: sse {} +- {} but exact {}",
+            mu,
+            beta,
+            stiffness,
+            error,
+            exact
+        );
+    }
+}
+
+#[test]
+fn unsupported_bosons_are_refused() {
+    let kind = |result: io::Result<BoseHubbard>| result.unwrap_err().kind();
+    assert_eq!(
+        kind(BoseHubbard::hard_core(0.0, 0.0, 0.0)),
+        io::ErrorKind::InvalidInput
+    );
+    assert_eq!(
+        kind(BoseHubbard::hard_core(1.0, f64::NAN, 0.0)),
+        io::ErrorKind::InvalidInput
+    );
+    let bosons = BoseHubbard::hard_core(1.0, 0.0, 0.0).unwrap();
+    assert_eq!(kind(bosons.soft_core(1.0, 0)), io::ErrorKind::InvalidInput);
+    assert_eq!(kind(bosons.soft_core(1.0, 7)), io::ErrorKind::InvalidInput);
+    let error = bosons
+        .run(
+            &make_jq_latice(4, 4, 1.0, 1.0).unwrap(),
+            1.0,
+            &monte_carlo(0),
+        )
+        .unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+}
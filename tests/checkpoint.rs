@@ -0,0 +1,229 @@
+use montecarlo::checkpoint::{Checkpoint, CHECKPOINT_VERSION};
+use montecarlo::geometry::UnitCell;
+use montecarlo::lattice::{make_jq_latice, make_latice, Boundary};
+use montecarlo::model::Model;
+use montecarlo::rng::chain_rng;
+use montecarlo::simulation::{MonteCarlo, Simulation};
+use montecarlo::state::State;
+use rand::Rng;
+use std::fs;
+use std::io;
+
+#[test]
+fn resumed_chain_matches_uninterrupted_run() {
+    // the second lattice also stores plaquette operators
+    for lattice in [
+        make_latice(4, 2, 0.7).unwrap(),
+        make_jq_latice(4, 2, 0.7, 1.5).unwrap(),
+    ] {
+        let start = || {
+            let mut rng = chain_rng(11, 0);
+            let s = State::new(&lattice, 10, &mut rng);
+            Checkpoint::new(s, rng, 4.0, 10)
+        };
+        let dir = std::env::temp_dir().join(format!("montecarlo-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("chain.json");
+
+        let mut uninterrupted = start();
+        uninterrupted
+            .run(400, 1000, &dir.join("uninterrupted.json"))
+            .unwrap();
+
+        let mut interrupted = start();
+        interrupted.run(150, 1000, &path).unwrap();
+        drop(interrupted);
+        let mut resumed = Checkpoint::load(&path).unwrap();
+        assert_eq!(resumed.samples.len(), 150);
+        resumed.run(400, 1000, &path).unwrap();
+
+        assert_eq!(resumed.samples, uninterrupted.samples);
+        assert_eq!(resumed.state.n, uninterrupted.state.n);
+        assert_eq!(resumed.rng.gen::<u64>(), uninterrupted.rng.gen::<u64>());
+
+        let data = fs::read_to_string(&path).unwrap();
+        let version = format!("\"version\":{}", CHECKPOINT_VERSION);
+        fs::write(&path, data.replacen(&version, "\"version\":0", 1)).unwrap();
+        assert!(Checkpoint::load(&path).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
+
+#[test]
+fn resumed_chain_keeps_the_geometry() {
+    let sqrt3 = 3f64.sqrt();
+    let lattice = UnitCell::new([[sqrt3, 0.0, 0.0], [sqrt3 / 2.0, 1.5, 0.0]])
+        .site([0.0, 0.0, 0.0])
+        .site([0.0, 1.0, 0.0])
+        .bond(0, 1, [0, 0], "z")
+        .bond(0, 1, [0, -1], "y")
+        .bond(0, 1, [1, -1], "x")
+        .repeat([3, 2], [Boundary::Periodic, Boundary::Twisted(1)])
+        .unwrap()
+        .coupling("z", 0.6)
+        .build()
+        .unwrap();
+    let start = || {
+        let mut rng = chain_rng(12, 0);
+        let s = State::new(&lattice, 10, &mut rng);
+        Checkpoint::new(s, rng, 2.0, 10)
+    };
+    let dir = std::env::temp_dir().join(format!("montecarlo-geometry-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("chain.json");
+
+    let mut uninterrupted = start();
+    uninterrupted
+        .run(200, 1000, &dir.join("uninterrupted.json"))
+        .unwrap();
+    start().run(100, 1000, &path).unwrap();
+    let mut resumed = Checkpoint::load(&path).unwrap();
+    let restored = &resumed.state.latice;
+    assert!(restored.wraps(0) && restored.wraps(1) && !restored.wraps(2));
+    assert_eq!(restored.boundaries, lattice.boundaries);
+    assert_eq!(restored.edge_types, lattice.edge_types);
+    assert_eq!(restored.positions, lattice.positions);
+    assert_eq!(restored.cells, lattice.cells);
+    resumed
+        .check_parameters(&lattice, &resumed.state.model, 2.0)
+        .unwrap();
+
+    resumed.run(200, 1000, &path).unwrap();
+    assert_eq!(resumed.samples, uninterrupted.samples);
+    assert!(Checkpoint::load(&path).unwrap().state.latice.wraps(1));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn damaged_checkpoints_are_errors() {
+    let lattice = make_jq_latice(4, 2, 0.7, 1.5).unwrap();
+    let mut rng = chain_rng(12, 0);
+    let mut s = State::new(&lattice, 10, &mut rng);
+    s.thermalize(2.0, &mut rng);
+    let dir = std::env::temp_dir().join(format!("montecarlo-damaged-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("chain.json");
+    Checkpoint::new(s, rng, 2.0, 10).save(&path).unwrap();
+    let saved: serde_json::Value = serde_json::from_slice(&fs::read(&path).unwrap()).unwrap();
+    let first_operator = saved["path"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .position(|op| !op.is_null() && op["plaquette"].is_null())
+        .unwrap();
+
+    type Damage = fn(&mut serde_json::Value, usize);
+    let damages: [(&str, Damage); 8] = [
+        ("edge out of range", |v, _| v["edges"][3]["odd"] = 99.into()),
+        ("edge ids", |v, _| v["edges"][3]["id"] = 2.into()),
+        ("spin", |v, _| v["model"]["spin"] = 0.7.into()),
+        ("edge types", |v, _| {
+            v["edge_types"].as_array_mut().unwrap().pop();
+        }),
+        ("plaquettes need spin 1/2", |v, _| {
+            v["model"]["spin"] = 1.0.into()
+        }),
+        ("sign problem", |v, _| {
+            v["edges"][0]["coupling"] = (-1.0).into()
+        }),
+        ("operator off the lattice", |v, op| {
+            v["path"][op]["edge"]["odd"] = 7.into()
+        }),
+        ("operator levels", |v, op| {
+            let leg = v["path"][op]["legs"][2].as_u64().unwrap();
+            v["path"][op]["legs"][2] = (1 - leg).into();
+        }),
+    ];
+    for (name, damage) in damages {
+        let mut value = saved.clone();
+        damage(&mut value, first_operator);
+        fs::write(&path, serde_json::to_vec(&value).unwrap()).unwrap();
+        let error = Checkpoint::load(&path)
+            .err()
+            .unwrap_or_else(|| panic!("{}", name));
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData, "{}", name);
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn resuming_with_other_parameters_is_an_error() {
+    let dir = std::env::temp_dir().join(format!("montecarlo-resume-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("chain.json");
+    let monte_carlo = MonteCarlo {
+        samples: 100,
+        nloop: 10,
+        bin_size: 10,
+        bootstraps: 10,
+        seed: 3,
+        chains: 1,
+        threads: 0,
+    };
+    let simulation = |lattice, beta| {
+        Simulation::new(lattice, beta)
+            .monte_carlo(monte_carlo.clone())
+            .checkpoint(path.clone(), 50)
+    };
+    simulation(make_latice(4, 2, 0.7).unwrap(), 2.0)
+        .run()
+        .unwrap();
+    simulation(make_latice(4, 2, 0.7).unwrap(), 2.0)
+        .run()
+        .unwrap();
+
+    let other_beta = simulation(make_latice(4, 2, 0.7).unwrap(), 3.0)
+        .run()
+        .unwrap_err();
+    assert_eq!(other_beta.kind(), io::ErrorKind::InvalidInput);
+    let other_coupling = simulation(make_latice(4, 2, 0.8).unwrap(), 2.0)
+        .run()
+        .unwrap_err();
+    assert_eq!(other_coupling.kind(), io::ErrorKind::InvalidInput);
+    let other_model = simulation(make_latice(4, 2, 0.7).unwrap(), 2.0)
+        .model(Model::heisenberg().spin(1.0))
+        .run()
+        .unwrap_err();
+    assert_eq!(other_model.kind(), io::ErrorKind::InvalidInput);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn replicas_checkpoint_to_their_own_files() {
+    let dir = std::env::temp_dir().join(format!("montecarlo-replicas-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("chain.json");
+    let monte_carlo = MonteCarlo {
+        samples: 200,
+        nloop: 10,
+        bin_size: 10,
+        bootstraps: 10,
+        seed: 5,
+        chains: 4,
+        threads: 4,
+    };
+    Simulation::new(make_latice(4, 2, 0.7).unwrap(), 2.0)
+        .monte_carlo(monte_carlo)
+        .checkpoint(path.clone(), 1)
+        .run()
+        .unwrap();
+    let mut files: Vec<_> = fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect();
+    files.sort();
+    assert_eq!(
+        files,
+        [
+            "chain.json.r0",
+            "chain.json.r1",
+            "chain.json.r2",
+            "chain.json.r3"
+        ]
+    );
+    for file in files {
+        let checkpoint = Checkpoint::load(&dir.join(file)).unwrap();
+        assert_eq!(checkpoint.samples.len(), 200);
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
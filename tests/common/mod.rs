@@ -0,0 +1,60 @@
+//! Seeded chains that the statistical tests compare against exact
+//! diagonalization.
+#![allow(dead_code)]
+
+use montecarlo::lattice::Lattice;
+use montecarlo::model::Model;
+use montecarlo::rng::chain_rng;
+use montecarlo::state::State;
+use montecarlo::stats;
+
+/// `(mean, error)` of every observable of a chain.
+pub struct Estimates {
+    pub energy: (f64, f64),
+    pub magnetization: (f64, f64),
+    pub staggered_magnetization: (f64, f64),
+}
+
+/// Binned bootstrap estimate of an observable, `(mean, error)`.
+fn estimate(samples: &[f64], seed: u64) -> (f64, f64) {
+    stats::bootstrap(
+        &stats::bin(samples, 100),
+        200,
+        &mut chain_rng(seed, u64::MAX),
+    )
+}
+
+/// Runs a thermalized chain of `model` with 20000 samples, verifies its final
+/// state and returns it with the estimates.
+pub fn run_chain(lattice: &Lattice, model: Model, beta: f64, seed: u64) -> (Estimates, State) {
+    let rng = &mut chain_rng(seed, beta as u64);
+    let mut s = State::with_model(lattice, model, 10, rng);
+    s.thermalize(beta, rng);
+    let mut energies = Vec::new();
+    let mut ms = Vec::new();
+    let mut sms = Vec::new();
+    for _ in 0..20000 {
+        let sample = s.sample(10, beta, rng);
+        energies.push(sample.energy);
+        ms.push(sample.magnetization);
+        sms.push(sample.staggered_magnetization);
+    }
+    s.verify();
+    let estimates = Estimates {
+        energy: estimate(&energies, seed),
+        magnetization: estimate(&ms, seed),
+        staggered_magnetization: estimate(&sms, seed),
+    };
+    (estimates, s)
+}
+
+pub fn assert_agrees(name: &str, (mean, error): (f64, f64), exact: f64) {
+    assert!(
+        (mean - exact).abs() < 4.0 * error + 1e-3,
+        "{}: sse {} +- {} but exact {}",
+        name,
+        mean,
+        error,
+        exact
+    );
+}
@@ -0,0 +1,133 @@
+use montecarlo::config::RunConfig;
+use std::fs;
+use std::path::Path;
+
+#[test]
+fn run_configs_execute_and_record_provenance() {
+    for name in ["question9", "question10"] {
+        RunConfig::load(
+            &Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("configs")
+                .join(format!("{}.toml", name)),
+        )
+        .unwrap();
+    }
+    let dir = std::env::temp_dir().join(format!("montecarlo-config-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("campaign.json");
+    let campaign = format!(
+        r#"{{
+            "output_dir": "{}",
+            "lattice": {{ "geometry": "columnar-dimer", "sizes": [2] }},
+            "model": {{ "j1": [0.5, 1.5] }},
+            "sweep": {{ "betas": [1.0] }},
+            "monte_carlo": {{ "samples": 200, "bin_size": 20, "bootstraps": 10 }}
+        }}"#,
+        dir.join("out").display()
+    );
+    fs::write(&path, campaign).unwrap();
+    let config = RunConfig::load(&path).unwrap();
+    config.execute().unwrap();
+
+    let resolved = RunConfig::load(&dir.join("out").join("config.toml")).unwrap();
+    assert_eq!(resolved.monte_carlo.nloop, 40);
+    assert_eq!(resolved.observables.len(), 3);
+    let results = fs::read_to_string(dir.join("out").join("results.csv")).unwrap();
+    assert_eq!(results.lines().count(), 2);
+    assert_eq!(results.lines().next().unwrap().split(',').count(), 9);
+
+    fs::write(&path, r#"{"output_dir": "x", "lattice": {"geometry": "columnar-dimer", "sizes": [2]}, "sweep": {}}"#).unwrap();
+    assert!(RunConfig::load(&path).is_err());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn geometries_take_j1_on_their_bonds() {
+    let dir = std::env::temp_dir().join(format!("montecarlo-geometries-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let graph = dir.join("ring.json");
+    fs::write(
+        &graph,
+        r#"{
+            "sites": [{ "label": "a" }, { "label": "b" }, { "label": "c" }, { "label": "d" }],
+            "edges": [
+                { "a": "a", "b": "b", "class": "j1" },
+                { "a": "b", "b": "c" },
+                { "a": "c", "b": "d", "class": "j1" },
+                { "a": "d", "b": "a" }
+            ]
+        }"#,
+    )
+    .unwrap();
+    let path = dir.join("campaign.toml");
+    let cases = [
+        ("\"square\"", 4, 32, 16),
+        ("\"ladder\"", 4, 12, 4),
+        ("\"bilayer\"", 2, 20, 4),
+        ("\"honeycomb\"", 3, 27, 9),
+        ("\"cubic\"", 2, 24, 8),
+        (&format!("{{ graph = {:?} }}", graph), 1, 4, 2),
+    ];
+    for (geometry, size, edges, j1_edges) in cases {
+        let campaign = format!(
+            "output_dir = {:?}\n\
+             [lattice]\ngeometry = {}\nsizes = [{}]\n\
+             [model]\nj1 = 0.5\n\
+             [sweep]\nbetas = [1.0]\n",
+            dir.join("out"),
+            geometry,
+            size
+        );
+        fs::write(&path, campaign).unwrap();
+        let config = RunConfig::load(&path).unwrap();
+        let lattice = config.lattice.build(size, 0.5, 0.0).unwrap();
+        assert_eq!(lattice.edges.len(), edges, "{}", geometry);
+        let j1 = lattice.edges.iter().filter(|e| e.coupling == 0.5).count();
+        assert_eq!(j1, j1_edges, "{}", geometry);
+
+        config.save(&path).unwrap();
+        assert_eq!(
+            RunConfig::load(&path).unwrap().lattice.geometry,
+            config.lattice.geometry
+        );
+    }
+
+    let plaquettes = "output_dir = \"x\"\n[lattice]\ngeometry = \"square\"\nsizes = [4]\n\
+                      [model]\nq = 1.0\n[sweep]\nbetas = [1.0]\n";
+    fs::write(&path, plaquettes).unwrap();
+    assert!(RunConfig::load(&path).is_err());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn unsupported_models_are_refused() {
+    let dir = std::env::temp_dir().join(format!("montecarlo-bad-config-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("campaign.toml");
+    let models = [
+        "spin = 0.7",
+        "spin = 4.0",
+        "epsilon = -1.0",
+        "j1 = []",
+        "j1 = { min = 0.0, max = 1.0, steps = 0 }",
+        "j1 = nan",
+        "field = inf",
+        "staggered_field = nan",
+    ];
+    for model in models {
+        let campaign = format!(
+            "output_dir = {:?}\n\
+             [lattice]\ngeometry = \"square\"\nsizes = [2]\n\
+             [model]\n{}\n\
+             [sweep]\nbetas = [1.0]\n",
+            dir.join("out"),
+            model
+        );
+        fs::write(&path, campaign).unwrap();
+        let error = RunConfig::load(&path)
+            .err()
+            .unwrap_or_else(|| panic!("{}", model));
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData, "{}", model);
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
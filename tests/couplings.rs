@@ -0,0 +1,145 @@
+mod common;
+
+use common::{assert_agrees, run_chain};
+use montecarlo::boson::BoseHubbard;
+use montecarlo::ed::ExactDiagonalization;
+use montecarlo::lattice::{make_jq_latice, make_latice, Lattice, LatticeConstructor, Site_Id};
+use montecarlo::model::Model;
+use montecarlo::rng::chain_rng;
+use montecarlo::simulation::{MonteCarlo, Simulation};
+use montecarlo::state::State;
+use montecarlo::tempering::Tempering;
+use montecarlo::vertex::Bond;
+
+/// Ring of `couplings.len()` sites where bond `i` joins sites `i` and `i + 1`.
+fn ring(couplings: &[f64]) -> Lattice {
+    let length = couplings.len();
+    let mut constructor = LatticeConstructor::new((0..length).collect());
+    for (i, &coupling) in couplings.iter().enumerate() {
+        constructor.add_edge(i, (i + 1) % length, coupling);
+    }
+    constructor.build().unwrap()
+}
+
+/// Two periodic legs of `length` sites joined by rungs.
+fn ladder(length: usize, leg: f64, rung: f64) -> Lattice {
+    let mut constructor =
+        LatticeConstructor::new((0..length).flat_map(|x| [(x, 0), (x, 1)]).collect());
+    for x in 0..length {
+        constructor.add_edge((x, 0), (x, 1), rung);
+        for y in 0..2 {
+            constructor.add_edge((x, y), ((x + 1) % length, y), leg);
+        }
+    }
+    constructor.build().unwrap()
+}
+
+#[test]
+fn mixed_sign_couplings_match_exact_diagonalization() {
+    let cases = [
+        (ladder(4, 1.0, -1.5), Model::heisenberg()),
+        (ladder(4, -0.7, 1.0), Model::xxz(0.6).field(0.4)),
+        (ring(&[-1.0; 6]), Model::heisenberg().spin(1.0).epsilon(0.1)),
+        (
+            ring(&[1.0, -0.5, 0.8, -1.2]),
+            Model::xxz(1.3).staggered_field(0.3),
+        ),
+    ];
+    for (seed, (lattice, model)) in cases.into_iter().enumerate() {
+        assert!(model.sign_free(&lattice));
+        for beta in [1.0, 4.0] {
+            let exact = ExactDiagonalization::with_model(&lattice, &model).thermodynamics(beta);
+            let (chain, _) = run_chain(&lattice, model, beta, seed as u64);
+            let name = format!("{:?} beta={} seed={}", model, beta, seed);
+            assert_agrees(&format!("energy {}", name), chain.energy, exact.energy);
+            assert_agrees(
+                &format!("magnetization {}", name),
+                chain.magnetization,
+                exact.magnetization,
+            );
+        }
+    }
+}
+
+#[test]
+fn frustrated_signs_are_refused() {
+    // an odd number of ferromagnetic bonds around a square
+    let frustrated = ring(&[1.0, 1.0, 1.0, -1.0]);
+    assert!(!Model::heisenberg().sign_free(&frustrated));
+    // the same holds for the hopping of bosons
+    assert!(!Model::heisenberg().bosons(1).sign_free(&frustrated));
+    assert!(Model::heisenberg()
+        .bosons(1)
+        .sign_free(&ring(&[1.0, 1.0, -1.0, -1.0])));
+    // a bond without coupling opens the cycle
+    assert!(Model::heisenberg().sign_free(&ring(&[1.0, 1.0, -1.0, 0.0])));
+
+    let monte_carlo = MonteCarlo {
+        samples: 10,
+        ..MonteCarlo::default()
+    };
+    let error = Simulation::new(frustrated.clone(), 1.0)
+        .monte_carlo(monte_carlo.clone())
+        .run()
+        .unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput);
+    // the other samplers refuse before any chain starts
+    let error = Tempering::in_beta(frustrated.clone(), &[1.0, 2.0])
+        .monte_carlo(monte_carlo.clone())
+        .run()
+        .unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput);
+    let error = BoseHubbard::hard_core(1.0, 0.0, 0.0)
+        .unwrap()
+        .run(&frustrated, 1.0, &monte_carlo)
+        .unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput);
+
+    // columnar dimers with ferromagnetic dimers put two of them on every square
+    assert!(Model::heisenberg().sign_free(&make_latice(4, 4, -0.5).unwrap()));
+}
+
+#[test]
+fn sign_problems_are_located() {
+    let lattice = ring(&[1.0, 1.0, -1.0, 1.0, 1.0, 1.0]);
+    let problem = Model::heisenberg().sign_problem(&lattice).unwrap();
+    let mut bonds = problem.bonds.clone();
+    bonds.sort_by_key(|b| match b {
+        Bond::Edge(id) => *id,
+        Bond::Plaquette(_) => panic!("{}", problem),
+    });
+    assert_eq!(bonds, (0..6).map(Bond::Edge).collect::<Vec<_>>());
+    assert_eq!(problem.sites.len(), 6);
+    for (k, bond) in problem.bonds.iter().enumerate() {
+        let Bond::Edge(id) = *bond else {
+            unreachable!()
+        };
+        let edge = lattice.edges[id];
+        let ends = [problem.sites[k], problem.sites[(k + 1) % 6]];
+        assert!(ends.contains(&Site_Id::Even(edge.even)), "{}", problem);
+        assert!(ends.contains(&Site_Id::Odd(edge.odd)), "{}", problem);
+    }
+
+    // singlet projectors on ferromagnetic dimers
+    let problem = Model::heisenberg()
+        .sign_problem(&make_jq_latice(4, 4, -0.5, 1.0).unwrap())
+        .unwrap();
+    assert!(
+        problem
+            .bonds
+            .iter()
+            .any(|b| matches!(b, Bond::Plaquette(_))),
+        "{}",
+        problem
+    );
+    assert!(Model::heisenberg()
+        .sign_problem(&make_jq_latice(4, 4, 0.5, 1.0).unwrap())
+        .is_none());
+}
+
+#[test]
+#[should_panic(expected = "sign problem")]
+fn states_refuse_frustrated_signs() {
+    let rng = &mut chain_rng(0, 0);
+    State::with_model(&ring(&[1.0, -1.0, 1.0, 1.0]), Model::heisenberg(), 10, rng);
+}
@@ -0,0 +1,58 @@
+mod common;
+
+use common::{assert_agrees, run_chain};
+use montecarlo::ed::ExactDiagonalization;
+use montecarlo::lattice::make_latice;
+use montecarlo::model::Model;
+use montecarlo::vertex::solve_directed_loop;
+
+#[test]
+fn directed_loop_solutions_are_symmetric_with_minimal_bounces() {
+    let cases: [[f64; 4]; 5] = [
+        [0.5, 0.0, 0.5, 0.0],
+        [1.0, 0.2, 0.3, 0.0],
+        [0.3, 0.7, 0.2, 0.4],
+        [0.25, 0.25, 0.25, 0.25],
+        [0.0, 0.0, 0.0, 0.0],
+    ];
+    for weights in cases {
+        let a = solve_directed_loop(&weights);
+        for i in 0..4 {
+            let row: f64 = (0..4).map(|j| a[i * 4 + j]).sum();
+            assert!((row - weights[i]).abs() < 1e-12, "{:?}", weights);
+            for j in 0..4 {
+                assert!(a[i * 4 + j] >= 0.0);
+                assert!((a[i * 4 + j] - a[j * 4 + i]).abs() < 1e-12);
+            }
+        }
+        let largest = weights.iter().cloned().fold(0.0, f64::max);
+        let bounce: f64 = (0..4).map(|i| a[i * 4 + i]).sum();
+        let minimal = (2.0 * largest - weights.iter().sum::<f64>()).max(0.0);
+        assert!((bounce - minimal).abs() < 1e-12, "{:?}", weights);
+    }
+}
+
+#[test]
+fn xxz_matches_exact_diagonalization() {
+    let lattice = make_latice(4, 2, 0.7).unwrap();
+    let models = [
+        Model::xxz(0.3),
+        Model::xxz(2.5),
+        Model::xxz(-0.5),
+        Model::xxz(0.0).epsilon(0.1),
+        Model::heisenberg().epsilon(0.25),
+    ];
+    for (seed, model) in models.into_iter().enumerate() {
+        for beta in [1.0, 4.0] {
+            let exact = ExactDiagonalization::with_model(&lattice, &model).thermodynamics(beta);
+            let (chain, _) = run_chain(&lattice, model, beta, seed as u64);
+            let name = format!("{:?} beta={}", model, beta);
+            assert_agrees(&format!("energy {}", name), chain.energy, exact.energy);
+            assert_agrees(
+                &format!("staggered magnetization {}", name),
+                chain.staggered_magnetization,
+                exact.staggered_magnetization,
+            );
+        }
+    }
+}
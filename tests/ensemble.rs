@@ -0,0 +1,80 @@
+use montecarlo::ed::ExactDiagonalization;
+use montecarlo::ensemble::Ensemble;
+use montecarlo::lattice::make_latice;
+use montecarlo::simulation::{MonteCarlo, Observable, Simulation};
+
+fn monte_carlo(chains: usize) -> MonteCarlo {
+    MonteCarlo {
+        samples: 4000,
+        nloop: 10,
+        bin_size: 100,
+        bootstraps: 200,
+        seed: 5,
+        chains,
+        threads: 0,
+    }
+}
+
+#[test]
+fn replicas_are_independent_of_the_thread_count() {
+    let simulation =
+        Simulation::new(make_latice(2, 2, 0.6).unwrap(), 2.0).monte_carlo(monte_carlo(4));
+    let run = |threads| {
+        let mut ensemble = Ensemble::new().threads(threads);
+        ensemble.push(simulation.clone().chain(3));
+        ensemble.run().unwrap().remove(0)
+    };
+    let serial = run(1);
+    let parallel = run(3);
+    assert_eq!(serial.energy, parallel.energy);
+    assert_eq!(serial.energy.chains().count(), 4);
+    assert_eq!(serial.energy.len(), 4 * 4000);
+    assert_eq!(
+        simulation.clone().chain(3).run().unwrap().energy,
+        serial.energy
+    );
+
+    // replica 0 is the chain itself
+    let single = simulation
+        .clone()
+        .monte_carlo(monte_carlo(1))
+        .chain(3)
+        .run()
+        .unwrap();
+    assert_eq!(
+        serial.energy.chains().next().unwrap(),
+        single.energy.samples()
+    );
+}
+
+#[test]
+fn merged_chains_match_exact_diagonalization() {
+    let mut ensemble = Ensemble::new();
+    let points = [(0.5, 1.0), (1.5, 4.0)];
+    for (chain, (j1, beta)) in points.iter().enumerate() {
+        ensemble.push(
+            Simulation::new(make_latice(4, 2, *j1).unwrap(), *beta)
+                .monte_carlo(monte_carlo(6))
+                .chain(chain as u64),
+        );
+    }
+    for ((j1, beta), mut measurements) in points.into_iter().zip(ensemble.run().unwrap()) {
+        let exact = ExactDiagonalization::new(&make_latice(4, 2, j1).unwrap()).thermodynamics(beta);
+        let single_error = measurements.energy.chains().next().map(|chain| {
+            let binned = montecarlo::stats::bin(chain, 100);
+            montecarlo::stats::calc_sd(&binned) / (binned.len() as f64).sqrt()
+        });
+        let (energy, error) = measurements.estimate(Observable::Energy);
+        assert!(
+            (energy - exact.energy).abs() < 4.0 * error + 1e-3,
+            "j1={} beta={}: sse {} +- {} but exact {}",
+            j1,
+            beta,
+            energy,
+            error,
+            exact.energy
+        );
+        // six chains shrink the error bar by about sqrt(6)
+        assert!(error < single_error.unwrap() * 0.7);
+    }
+}
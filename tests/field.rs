@@ -0,0 +1,39 @@
+mod common;
+
+use common::{assert_agrees, run_chain};
+use montecarlo::ed::ExactDiagonalization;
+use montecarlo::lattice::make_latice;
+use montecarlo::model::Model;
+
+#[test]
+fn fields_match_exact_diagonalization() {
+    let lattice = make_latice(4, 2, 0.7).unwrap();
+    let models = [
+        Model::heisenberg().field(0.5),
+        Model::heisenberg().field(1.8),
+        Model::xxz(0.4).field(-1.2),
+        Model::heisenberg().staggered_field(0.6),
+        Model::xxz(1.5)
+            .field(0.9)
+            .staggered_field(-0.4)
+            .epsilon(0.1),
+    ];
+    for (seed, model) in models.into_iter().enumerate() {
+        for beta in [1.0, 4.0] {
+            let exact = ExactDiagonalization::with_model(&lattice, &model).thermodynamics(beta);
+            let (chain, _) = run_chain(&lattice, model, beta, seed as u64);
+            let name = format!("{:?} beta={}", model, beta);
+            assert_agrees(&format!("energy {}", name), chain.energy, exact.energy);
+            assert_agrees(
+                &format!("magnetization {}", name),
+                chain.magnetization,
+                exact.magnetization,
+            );
+            assert_agrees(
+                &format!("staggered magnetization {}", name),
+                chain.staggered_magnetization,
+                exact.staggered_magnetization,
+            );
+        }
+    }
+}
@@ -0,0 +1,304 @@
+use montecarlo::geometry::{
+    bilayer, chain, cubic, honeycomb, ladder, square, square_dimers, DimerPattern, UnitCell,
+};
+use montecarlo::lattice::{make_latice, Boundary, CellSite, EdgeType, Lattice, LatticeError};
+
+use Boundary::{Open, Periodic, Twisted};
+
+/// Number of edges of type `edge_type` at every site, even sites first.
+fn degrees(lattice: &Lattice, edge_type: Option<&str>) -> Vec<usize> {
+    let mut degree = vec![0; lattice.num_even + lattice.num_odd];
+    for (edge, t) in lattice.edges.iter().zip(lattice.edge_types.iter()) {
+        if edge_type.is_none() || t.as_ref().map(|t| t.0.as_str()) == edge_type {
+            degree[edge.even.0] += 1;
+            degree[lattice.num_even + edge.odd.0] += 1;
+        }
+    }
+    degree
+}
+
+/// Checks that every bond has unit length once the torus spanned by `periods`
+/// is unwrapped.
+fn assert_unit_bonds(lattice: &Lattice, periods: &[[f64; 3]]) {
+    let positions = lattice.positions.as_ref().unwrap();
+    for edge in lattice.edges.iter() {
+        let (a, b) = (positions.even[edge.even], positions.odd[edge.odd]);
+        let shifts = [-1.0, 0.0, 1.0];
+        let unit = shifts.iter().any(|&n| {
+            shifts.iter().any(|&m| {
+                shifts.iter().any(|&k| {
+                    let mut length = 0.0;
+                    for axis in 0..3 {
+                        let mut d = b[axis] - a[axis];
+                        for (period, shift) in periods.iter().zip([n, m, k]) {
+                            d += shift * period[axis];
+                        }
+                        length += d * d;
+                    }
+                    (length - 1.0).abs() < 1e-9
+                })
+            })
+        });
+        assert!(unit, "{:?} -> {:?}", a, b);
+    }
+}
+
+#[test]
+fn chains_and_ladders() {
+    assert_eq!(chain(6, Open).unwrap().build().unwrap().edges.len(), 5);
+    let ring = chain(6, Periodic).unwrap().build().unwrap();
+    assert_eq!(ring.edges.len(), 6);
+    assert!(degrees(&ring, Some("bond")).iter().all(|&d| d == 2));
+    assert!(matches!(
+        chain(5, Periodic),
+        Err(LatticeError::BadPeriod { axis: 0, length: 5 })
+    ));
+
+    let constructor = ladder(4, 3, Open).unwrap();
+    assert_eq!(
+        constructor.edge_types(),
+        vec![EdgeType::from("leg"), EdgeType::from("rung")]
+    );
+    let lattice = constructor.coupling("rung", 0.5).build().unwrap();
+    assert_eq!(lattice.edges.len(), 3 * 3 + 4 * 2);
+    for (edge, edge_type) in lattice.edges.iter().zip(lattice.edge_types.iter()) {
+        let expected = match edge_type.as_ref().unwrap().0.as_str() {
+            "leg" => 1.0,
+            "rung" => 0.5,
+            other => panic!("unexpected edge type {}", other),
+        };
+        assert_eq!(edge.coupling, expected);
+    }
+    assert_unit_bonds(&lattice, &[]);
+    let periodic = ladder(4, 2, Periodic).unwrap().build().unwrap();
+    assert_eq!(degrees(&periodic, None), vec![3; 8]);
+    assert_unit_bonds(&periodic, &[[4.0, 0.0, 0.0]]);
+}
+
+#[test]
+fn unknown_edge_types_are_refused() {
+    let error = ladder(4, 1, Open)
+        .unwrap()
+        .coupling("rung", 0.5)
+        .coupling("leg", 2.0)
+        .build()
+        .unwrap_err();
+    assert_eq!(error, LatticeError::UnknownEdgeType(EdgeType::from("rung")));
+    assert_eq!(error.to_string(), "No edge of type rung");
+}
+
+#[test]
+fn square_lattices_and_dimer_patterns() {
+    let lattice = square(4, 6, [Periodic; 2]).unwrap().build().unwrap();
+    assert_eq!(degrees(&lattice, Some("x")), vec![2; 24]);
+    assert_unit_bonds(&lattice, &[[4.0, 0.0, 0.0], [0.0, 6.0, 0.0]]);
+
+    for (pattern, per_site) in [
+        (DimerPattern::Columnar, 1),
+        (DimerPattern::Staggered, 1),
+        (DimerPattern::Plaquette, 2),
+    ] {
+        let lattice = square_dimers(4, 4, pattern, [Periodic; 2])
+            .unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(
+            degrees(&lattice, Some("dimer")),
+            vec![per_site; 16],
+            "{:?}",
+            pattern
+        );
+        assert_eq!(degrees(&lattice, None), vec![4; 16]);
+    }
+
+    // the columnar pattern is the one of `make_latice`
+    let reference = make_latice(4, 4, 0.3).unwrap();
+    let lattice = square_dimers(4, 4, DimerPattern::Columnar, [Periodic; 2])
+        .unwrap()
+        .coupling("dimer", 0.3)
+        .build()
+        .unwrap();
+    for (a, b) in reference.edges.iter().zip(lattice.edges.iter()) {
+        assert_eq!((a.even, a.odd, a.coupling), (b.even, b.odd, b.coupling));
+    }
+}
+
+#[test]
+fn three_dimensional_and_honeycomb_lattices() {
+    let lattice = bilayer(4, 4, [Periodic; 2])
+        .unwrap()
+        .coupling("interlayer", 2.0)
+        .build()
+        .unwrap();
+    assert_eq!(degrees(&lattice, None), vec![5; 32]);
+    assert_eq!(degrees(&lattice, Some("interlayer")), vec![1; 32]);
+    assert_unit_bonds(&lattice, &[[4.0, 0.0, 0.0], [0.0, 4.0, 0.0]]);
+
+    let lattice = cubic(4, 4, 2, [Periodic; 3]).unwrap().build().unwrap();
+    assert_eq!(lattice.num_even, 16);
+    assert_eq!(degrees(&lattice, Some("z")), vec![2; 32]);
+    assert_eq!(degrees(&lattice, None), vec![6; 32]);
+
+    let lattice = honeycomb(3, 4, [Periodic; 2]).unwrap().build().unwrap();
+    assert_eq!((lattice.num_even, lattice.num_odd), (12, 12));
+    for edge_type in ["x", "y", "z"] {
+        assert_eq!(degrees(&lattice, Some(edge_type)), vec![1; 24]);
+    }
+    let sqrt3 = 3f64.sqrt();
+    assert_unit_bonds(
+        &lattice,
+        &[[3.0 * sqrt3, 0.0, 0.0], [4.0 * sqrt3 / 2.0, 4.0 * 1.5, 0.0]],
+    );
+}
+
+#[test]
+fn boundaries_are_chosen_per_axis() {
+    // a cylinder, periodic around x only
+    let lattice = square(4, 3, [Periodic, Open]).unwrap().build().unwrap();
+    assert_eq!(lattice.edges.len(), 4 * 3 + 4 * 2);
+    assert_eq!(lattice.boundaries, [Periodic, Open, Open]);
+    assert!(lattice.wraps(0) && !lattice.wraps(1) && !lattice.wraps(2));
+    let open = square(3, 3, [Open; 2]).unwrap().build().unwrap();
+    assert_eq!(open.edges.len(), 2 * 2 * 3);
+    assert_unit_bonds(&open, &[]);
+
+    // crossing the top shifts the sites by 2 along x
+    let twisted = square(4, 4, [Periodic, Twisted(2)]).unwrap();
+    let lattice = twisted.build().unwrap();
+    assert_eq!(degrees(&lattice, None), vec![4; 16]);
+    assert_unit_bonds(&lattice, &[[4.0, 0.0, 0.0], [2.0, 4.0, 0.0]]);
+    assert_eq!(lattice.boundaries[1], Twisted(2));
+    let lattice = honeycomb(3, 3, [Periodic, Twisted(1)])
+        .unwrap()
+        .build()
+        .unwrap();
+    assert_eq!(degrees(&lattice, None), vec![3; 18]);
+
+    assert!(matches!(
+        square(3, 4, [Periodic; 2]),
+        Err(LatticeError::BadPeriod { axis: 0, length: 3 })
+    ));
+    assert!(square(3, 4, [Open, Periodic]).is_ok());
+    assert!(matches!(
+        square(4, 4, [Periodic, Twisted(1)]),
+        Err(LatticeError::BadTwist { axis: 1, shift: 1 })
+    ));
+    assert!(square(4, 3, [Periodic, Twisted(1)]).is_ok());
+    assert!(matches!(
+        square(4, 4, [Twisted(2), Periodic]),
+        Err(LatticeError::BadTwist { axis: 0, .. })
+    ));
+    assert!(matches!(
+        square(4, 4, [Open, Twisted(2)]),
+        Err(LatticeError::BadTwist { axis: 1, .. })
+    ));
+    assert!(matches!(
+        cubic(2, 0, 2, [Open; 3]),
+        Err(LatticeError::EmptyAxis(1))
+    ));
+    assert!(honeycomb(3, 5, [Periodic; 2]).is_ok());
+}
+
+/// Asserts that `a` and `b` have the same sites, positions and edges.
+fn assert_same_lattice(a: &Lattice, b: &Lattice) {
+    assert_eq!((a.num_even, a.num_odd), (b.num_even, b.num_odd));
+    assert_eq!(a.positions, b.positions);
+    assert_eq!(a.edge_types, b.edge_types);
+    for (x, y) in a.edges.iter().zip(b.edges.iter()) {
+        assert_eq!(
+            (x.even, x.odd, x.displacement),
+            (y.even, y.odd, y.displacement)
+        );
+    }
+    assert_eq!(a.edges.len(), b.edges.len());
+}
+
+#[test]
+fn unit_cells_repeat_along_lattice_vectors() {
+    let cell = UnitCell::new([[1.0, 0.0, 0.0], [0.0, 1.0, 0.0]])
+        .site([0.0; 3])
+        .bond(0, 0, [1, 0], "x")
+        .bond(0, 0, [0, 1], "y");
+    for boundaries in [[Periodic; 2], [Open, Periodic], [Periodic, Twisted(2)]] {
+        let lattice = cell.repeat([4, 6], boundaries).unwrap().build().unwrap();
+        assert_same_lattice(
+            &lattice,
+            &square(4, 6, boundaries).unwrap().build().unwrap(),
+        );
+        assert_eq!(lattice.boundaries[..2], boundaries);
+    }
+
+    let sqrt3 = 3f64.sqrt();
+    let cell = UnitCell::new([[sqrt3, 0.0, 0.0], [sqrt3 / 2.0, 1.5, 0.0]])
+        .site([0.0, 0.0, 0.0])
+        .site([0.0, 1.0, 0.0])
+        .bond(0, 1, [0, 0], "z")
+        .bond(0, 1, [0, -1], "y")
+        .bond(0, 1, [1, -1], "x");
+    for boundaries in [[Periodic; 2], [Open; 2], [Periodic, Twisted(1)]] {
+        let lattice = cell.repeat([3, 5], boundaries).unwrap().build().unwrap();
+        let reference = honeycomb(3, 5, boundaries).unwrap().build().unwrap();
+        assert_same_lattice(&lattice, &reference);
+        assert!(reference.cells.is_none());
+        // the basis sites are the sublattices, the cells are in order
+        let cells = lattice.cells.unwrap();
+        for (id, site) in cells.odd.iter() {
+            let cell = [id.0 / 5, id.0 % 5, 0];
+            assert_eq!(*site, CellSite { cell, basis: 1 });
+        }
+        assert_eq!(cells.even.len(), 15);
+    }
+}
+
+#[test]
+fn unit_cells_are_checked_when_built() {
+    let triangular = UnitCell::new([[1.0, 0.0, 0.0], [0.5, 0.75f64.sqrt(), 0.0]])
+        .site([0.0; 3])
+        .bond(0, 0, [1, 0], "a")
+        .bond(0, 0, [0, 1], "b")
+        .bond(0, 0, [1, -1], "c");
+    match triangular.repeat([4, 4], [Open; 2]).unwrap().build() {
+        Err(LatticeError::NotBipartite(odd)) => assert_eq!(odd.cycle.len(), 3),
+        other => panic!("{:?}", other.map(|l| l.edges.len())),
+    }
+
+    // a chain with two sites per cell and bonds reaching two cells on
+    let chain = UnitCell::new([[2.0, 0.0, 0.0]])
+        .site([0.0; 3])
+        .site([1.0, 0.0, 0.0])
+        .bond(0, 1, [0], "intra")
+        .bond(1, 0, [1], "inter")
+        .bond(0, 1, [2], "far");
+    let lattice = chain.repeat([4], [Open]).unwrap().build().unwrap();
+    assert_eq!(degrees(&lattice, Some("far")).iter().sum::<usize>(), 2 * 2);
+    let cells = lattice.cells.as_ref().unwrap();
+    let positions = lattice.positions.as_ref().unwrap();
+    for (id, site) in cells.odd.iter() {
+        assert_eq!(positions.odd[id][0], 2.0 * site.cell[0] as f64 + 1.0);
+    }
+    // on a single periodic cell the bonds only differ in how they wind
+    let ring = chain.repeat([1], [Periodic]).unwrap().build().unwrap();
+    let windings: Vec<i32> = ring.edges.iter().map(|e| e.displacement[0]).collect();
+    assert_eq!(windings, vec![0, -1, 2]);
+    assert!(matches!(
+        chain
+            .clone()
+            .bond(1, 0, [1], "again")
+            .repeat([1], [Periodic])
+            .unwrap()
+            .build(),
+        Err(LatticeError::DuplicateEdge(..))
+    ));
+    assert!(matches!(
+        chain.repeat([0], [Open]),
+        Err(LatticeError::EmptyAxis(0))
+    ));
+}
+
+#[test]
+#[should_panic(expected = "No basis site 1")]
+fn bonds_need_basis_sites() {
+    UnitCell::new([[1.0, 0.0, 0.0]])
+        .site([0.0; 3])
+        .bond(0, 1, [1], "bond");
+}
@@ -0,0 +1,118 @@
+use montecarlo::geometry::{chain, honeycomb};
+use montecarlo::graph::{Graph, Sublattice};
+use montecarlo::lattice::{make_jq_latice, Boundary, EdgeType, Lattice, LatticeError};
+use std::fs;
+
+/// Asserts that `a` and `b` have the same sites, edges and plaquettes.
+fn assert_same_lattice(a: &Lattice, b: &Lattice) {
+    assert_eq!((a.num_even, a.num_odd), (b.num_even, b.num_odd));
+    assert_eq!(a.positions, b.positions);
+    assert_eq!(a.edge_types, b.edge_types);
+    assert_eq!(a.boundaries, b.boundaries);
+    let edge = |e: &montecarlo::lattice::Edge| (e.even, e.odd, e.coupling, e.displacement);
+    assert_eq!(
+        a.edges.iter().map(edge).collect::<Vec<_>>(),
+        b.edges.iter().map(edge).collect::<Vec<_>>()
+    );
+    let plaquette = |p: &montecarlo::lattice::Plaquette| (p.coupling, p.edges.map(|e| e.id));
+    assert_eq!(
+        a.plaquettes.iter().map(plaquette).collect::<Vec<_>>(),
+        b.plaquettes.iter().map(plaquette).collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn exported_lattices_are_imported_unchanged() {
+    let dir = std::env::temp_dir().join(format!("montecarlo-graph-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("lattice.json");
+    let lattices = [
+        make_jq_latice(4, 4, 0.5, 1.5).unwrap(),
+        honeycomb(3, 2, [Boundary::Periodic, Boundary::Twisted(1)])
+            .unwrap()
+            .coupling("x", -0.5)
+            .build()
+            .unwrap(),
+    ];
+    for lattice in lattices {
+        let graph = Graph::from_lattice(&lattice);
+        graph.save(&path).unwrap();
+        let loaded = Graph::load(&path).unwrap();
+        assert_eq!(loaded, graph);
+        let imported = loaded.constructor().unwrap().build().unwrap();
+        assert_same_lattice(&imported, &lattice);
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn exports_carry_labels_and_coloring() {
+    let constructor = chain(4, Boundary::Periodic).unwrap();
+    let (even, odd) = constructor.get_bipartite_coloring().unwrap();
+    let lattice = constructor.build().unwrap();
+    let graph = Graph::from_labelled_lattice(&lattice, &even, &odd).unwrap();
+    let error = Graph::from_labelled_lattice(&lattice, &even, &odd[1..]).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput);
+    let labels: Vec<(&str, Sublattice)> = graph
+        .sites
+        .iter()
+        .map(|s| (s.label.as_str(), s.sublattice.unwrap()))
+        .collect();
+    assert_eq!(
+        labels,
+        vec![
+            ("0", Sublattice::Even),
+            ("2", Sublattice::Even),
+            ("1", Sublattice::Odd),
+            ("3", Sublattice::Odd),
+        ]
+    );
+    assert_eq!(graph.sites[1].position, Some([2.0, 0.0, 0.0]));
+    let closing = &graph.edges[3];
+    assert_eq!((closing.a.as_str(), closing.b.as_str()), ("0", "3"));
+    assert_eq!(closing.displacement, [-1, 0, 0]);
+    assert_eq!(closing.class.as_deref(), Some("bond"));
+}
+
+#[test]
+fn irregular_graphs_are_imported() {
+    // a diluted plaquette with a dangling site, couplings from classes
+    let graph: Graph = serde_json::from_str(
+        r#"{
+            "sites": [{ "label": "a" }, { "label": "b" }, { "label": "c" }, { "label": "d" }],
+            "classes": { "strong": 2.0 },
+            "edges": [
+                { "a": "a", "b": "b", "class": "strong" },
+                { "a": "b", "b": "c", "class": "weak" },
+                { "a": "c", "b": "d", "class": "strong", "coupling": -0.5 }
+            ]
+        }"#,
+    )
+    .unwrap();
+    let constructor = graph.constructor().unwrap();
+    let (even, odd) = constructor.get_bipartite_coloring().unwrap();
+    assert_eq!(
+        (even, odd),
+        (vec!["a".into(), "c".into()], vec!["b".into(), "d".into()])
+    );
+    let lattice = constructor.build().unwrap();
+    let couplings: Vec<f64> = lattice.edges.iter().map(|e| e.coupling).collect();
+    assert_eq!(couplings, vec![2.0, 1.0, -0.5]);
+    assert_eq!(lattice.edge_types[1], Some(EdgeType::from("weak")));
+    assert!(lattice.positions.is_none());
+
+    let mut broken = graph.clone();
+    broken.edges[1].b = "e".to_string();
+    assert!(matches!(
+        broken.constructor().unwrap().build(),
+        Err(LatticeError::UnknownNode(node)) if node == "e"
+    ));
+    let mut twice = graph.clone();
+    twice.sites[3].label = "a".to_string();
+    assert!(twice.constructor().is_err());
+    let mut placed = graph.clone();
+    placed.sites[0].position = Some([0.0; 3]);
+    let error = placed.validate().unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+    assert!(serde_json::from_str::<Graph>(r#"{ "sites": [], "edges": [], "bonds": [] }"#).is_err());
+}
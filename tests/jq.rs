@@ -0,0 +1,28 @@
+mod common;
+
+use common::{assert_agrees, run_chain};
+use montecarlo::ed::ExactDiagonalization;
+use montecarlo::lattice::make_jq_latice;
+use montecarlo::model::Model;
+
+#[test]
+fn jq_model_matches_exact_diagonalization() {
+    for (seed, (j1, q)) in [(1.0, 1.0), (0.5, 4.0), (0.0, 2.0)].into_iter().enumerate() {
+        let lattice = make_jq_latice(4, 2, j1, q).unwrap();
+        for beta in [1.0, 4.0] {
+            let exact = ExactDiagonalization::new(&lattice).thermodynamics(beta);
+            let (chain, s) = run_chain(&lattice, Model::heisenberg(), beta, seed as u64);
+            assert!(s
+                .path
+                .iter()
+                .any(|(_, op)| op.as_ref().is_some_and(|op| op.plaquette.is_some())));
+            let name = format!("j1={} q={} beta={}", j1, q, beta);
+            assert_agrees(&format!("energy {}", name), chain.energy, exact.energy);
+            assert_agrees(
+                &format!("staggered magnetization {}", name),
+                chain.staggered_magnetization,
+                exact.staggered_magnetization,
+            );
+        }
+    }
+}
@@ -0,0 +1,239 @@
+use montecarlo::lattice::{
+    make_latice, new_rectangle, Edge, Even_Site_Id, Lattice, LatticeConstructor, LatticeError,
+    NotBipartite, Odd_Site_Id, Plaquette, Site_Id,
+};
+
+/// Checks that `cycle` is a closed walk along `edges` of odd length.
+fn assert_odd_cycle<T: PartialEq + std::fmt::Debug>(cycle: &[T], edges: &[(T, T)]) {
+    assert_eq!(cycle.len() % 2, 1, "{:?}", cycle);
+    for (k, a) in cycle.iter().enumerate() {
+        let b = &cycle[(k + 1) % cycle.len()];
+        assert!(
+            edges
+                .iter()
+                .any(|(x, y)| (x == a && y == b) || (x == b && y == a)),
+            "{:?} -> {:?} is not an edge",
+            a,
+            b
+        );
+    }
+}
+
+#[test]
+fn odd_cycles_are_reported() {
+    // a square with a pentagon hanging off one corner
+    let edges = [
+        (0, 1),
+        (1, 2),
+        (2, 3),
+        (3, 0),
+        (2, 4),
+        (4, 5),
+        (5, 6),
+        (6, 7),
+        (7, 2),
+    ];
+    let mut constructor = LatticeConstructor::new((0..8).collect());
+    for &(a, b) in edges.iter() {
+        constructor.add_edge(a, b, 1.0);
+    }
+    let Err(LatticeError::NotBipartite(NotBipartite { cycle })) = constructor.build() else {
+        panic!("the pentagon is not bipartite")
+    };
+    assert_eq!(cycle.len(), 5, "{:?}", cycle);
+    assert_odd_cycle(&cycle, &edges);
+
+    // a torus of odd width
+    let mut constructor = new_rectangle(3, 2);
+    let mut edges = Vec::new();
+    for x in 0..3 {
+        edges.push(((x, 0), ((x + 1) % 3, 0)));
+        edges.push(((x, 0), (x, 1)));
+    }
+    for &(a, b) in edges.iter() {
+        constructor.add_edge(a, b, 1.0);
+    }
+    let Err(LatticeError::NotBipartite(NotBipartite { cycle })) =
+        constructor.get_bipartite_coloring()
+    else {
+        panic!("a torus of odd width is not bipartite")
+    };
+    assert_odd_cycle(&cycle, &edges);
+}
+
+#[test]
+fn coloring_follows_the_order_of_the_nodes() {
+    let mut constructor = LatticeConstructor::new(vec!['a', 'b', 'c', 'd', 'e']);
+    constructor.add_edge('d', 'c', 1.0);
+    constructor.add_edge('a', 'b', 1.0);
+    constructor.add_edge('b', 'c', 1.0);
+    let (even, odd) = constructor.get_bipartite_coloring().unwrap();
+    assert_eq!(even, vec!['a', 'c', 'e']);
+    assert_eq!(odd, vec!['b', 'd']);
+}
+
+#[test]
+fn invalid_graphs_are_rejected() {
+    let square = |extra: &[(usize, usize)]| {
+        let mut constructor = LatticeConstructor::new((0..4).collect());
+        for &(a, b) in [(0, 1), (1, 2), (2, 3), (3, 0)].iter().chain(extra) {
+            constructor.add_edge(a, b, 1.0);
+        }
+        constructor
+    };
+    assert!(square(&[]).build().is_ok());
+    assert_eq!(
+        square(&[(2, 7)]).build().unwrap_err(),
+        LatticeError::UnknownNode(7)
+    );
+    assert_eq!(
+        square(&[(1, 1)]).build().unwrap_err(),
+        LatticeError::SelfLoop(1)
+    );
+    assert_eq!(
+        square(&[(2, 1)]).build().unwrap_err(),
+        LatticeError::DuplicateEdge(2, 1)
+    );
+
+    let mut isolated = LatticeConstructor::new(vec!['a', 'b', 'c']);
+    isolated.add_edge('a', 'b', 1.0);
+    assert_eq!(
+        isolated.build().unwrap_err(),
+        LatticeError::IsolatedSite('c')
+    );
+    assert_eq!(
+        LatticeConstructor::<usize>::new(Vec::new())
+            .build()
+            .unwrap_err(),
+        LatticeError::Empty
+    );
+    let mut uncoupled = LatticeConstructor::new(vec![0, 1]);
+    uncoupled.add_edge(0, 1, 0.0);
+    assert_eq!(uncoupled.build().unwrap_err(), LatticeError::Empty);
+
+    let mut plaquette = square(&[]);
+    plaquette.add_plaquette((0, 1), (2, 3), 1.0);
+    assert!(plaquette.build().is_ok());
+    let mut plaquette = square(&[]);
+    plaquette.add_plaquette((0, 1), (0, 2), 1.0);
+    assert_eq!(
+        plaquette.build().unwrap_err(),
+        LatticeError::NotAnEdge(0, 2)
+    );
+    let mut plaquette = square(&[]);
+    plaquette.add_plaquette((0, 1), (1, 2), 1.0);
+    assert_eq!(
+        plaquette.build().unwrap_err(),
+        LatticeError::SharedPlaquetteSite(1)
+    );
+    let mut plaquette = square(&[]);
+    plaquette.add_plaquette((0, 1), (2, 3), -1.0);
+    assert_eq!(
+        plaquette.build().unwrap_err(),
+        LatticeError::NegativePlaquette(0, 1)
+    );
+
+    let mut infinite = square(&[]);
+    infinite.add_edge(0, 2, f64::INFINITY);
+    assert_eq!(
+        infinite.build().unwrap_err(),
+        LatticeError::NonFiniteCoupling(0, 2)
+    );
+    let mut misplaced = LatticeConstructor::with_positions(vec![0, 1], vec![[0.0; 3]]);
+    misplaced.add_edge(0, 1, 1.0);
+    assert_eq!(
+        misplaced.build().unwrap_err(),
+        LatticeError::PositionCount {
+            nodes: 2,
+            positions: 1
+        }
+    );
+    assert_eq!(
+        square(&[]).with_cells(Vec::new()).build().unwrap_err(),
+        LatticeError::CellCount { nodes: 4, cells: 0 }
+    );
+    assert_eq!(
+        make_latice(3, 4, 0.5).unwrap_err(),
+        LatticeError::BadPeriod { axis: 0, length: 3 }
+    );
+}
+
+#[test]
+fn built_lattices_are_checked() {
+    let edge = |id, even, odd, coupling| Edge {
+        id,
+        coupling,
+        displacement: [0; 3],
+        even: Even_Site_Id(even),
+        odd: Odd_Site_Id(odd),
+    };
+    let lattice = Lattice::new(2, 2, vec![edge(0, 0, 0, 1.0), edge(1, 1, 1, 1.0)]).unwrap();
+    assert_eq!(
+        Lattice::new(2, 2, vec![edge(1, 0, 0, 1.0)]).unwrap_err(),
+        LatticeError::MisplacedId(1)
+    );
+    assert_eq!(
+        Lattice::new(2, 2, vec![edge(0, 0, 2, 1.0)]).unwrap_err(),
+        LatticeError::UnknownNode(Site_Id::Odd(Odd_Site_Id(2)))
+    );
+    assert_eq!(
+        Lattice::new(2, 2, vec![edge(0, 0, 0, 0.0)]).unwrap_err(),
+        LatticeError::Empty
+    );
+    assert_eq!(lattice.scaled(0.0).unwrap_err(), LatticeError::Empty);
+
+    let plaquette = |coupling, edges| Plaquette {
+        id: 0,
+        coupling,
+        edges,
+    };
+    let [a, b] = [lattice.edges[0], lattice.edges[1]];
+    let plaquettes = lattice
+        .clone()
+        .with_plaquettes(vec![plaquette(1.0, [a, b])])
+        .unwrap();
+    assert_eq!(plaquettes.scaled(2.0).unwrap().plaquettes[0].coupling, 2.0);
+    assert_eq!(
+        plaquettes.scaled(-1.0).unwrap_err(),
+        LatticeError::NegativePlaquette(Site_Id::Even(a.even), Site_Id::Odd(a.odd))
+    );
+    assert_eq!(
+        lattice
+            .with_plaquettes(vec![plaquette(1.0, [a, a])])
+            .unwrap_err(),
+        LatticeError::SharedPlaquetteSite(Site_Id::Even(a.even))
+    );
+}
+
+#[test]
+fn parallel_edges_that_wind_differently_are_distinct() {
+    let mut constructor = LatticeConstructor::new(vec![0, 1]);
+    constructor.add_displaced_edge(0, 1, 1.0, [1, 0, 0]);
+    constructor.add_displaced_edge(1, 0, 1.0, [1, 0, 0]);
+    let lattice = constructor.build().unwrap();
+    assert_eq!(lattice.edges[0].displacement, [1, 0, 0]);
+    assert_eq!(lattice.edges[1].displacement, [-1, 0, 0]);
+
+    constructor = LatticeConstructor::new(vec![0, 1]);
+    constructor.add_displaced_edge(0, 1, 1.0, [1, 0, 0]);
+    constructor.add_displaced_edge(1, 0, 1.0, [-1, 0, 0]);
+    assert_eq!(
+        constructor.build().unwrap_err(),
+        LatticeError::DuplicateEdge(1, 0)
+    );
+}
+
+#[test]
+fn large_lattices_build_quickly() {
+    // a quadratic construction would take hours here
+    let (width, height) = (1024, 512);
+    let lattice = make_latice(width, height, 0.5).unwrap();
+    assert_eq!(lattice.num_even, width * height / 2);
+    assert_eq!(lattice.num_odd, width * height / 2);
+    assert_eq!(lattice.edges.len(), 2 * width * height);
+    let mut degree = vec![0; lattice.num_even];
+    for edge in lattice.edges.iter() {
+        degree[edge.even.0] += 1;
+    }
+    assert!(degree.iter().all(|&d| d == 4));
+}
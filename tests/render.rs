@@ -0,0 +1,77 @@
+use montecarlo::geometry::{bilayer, square_dimers, DimerPattern};
+use montecarlo::graph::Graph;
+use montecarlo::lattice::{make_latice, Boundary};
+use montecarlo::render;
+
+#[test]
+fn dot_styles_sites_and_bonds() {
+    let lattice = square_dimers(4, 4, DimerPattern::Staggered, [Boundary::Periodic; 2])
+        .unwrap()
+        .coupling("dimer", -2.0)
+        .build()
+        .unwrap();
+    let dot = render::dot(&Graph::from_lattice(&lattice));
+    assert!(dot.starts_with("graph lattice {") && dot.ends_with("}\n"));
+    let lines: Vec<&str> = dot.lines().collect();
+    let sites: Vec<&&str> = lines.iter().filter(|l| l.contains("fillcolor")).collect();
+    assert_eq!(sites.len(), 16);
+    assert_eq!(sites.iter().filter(|l| l.contains("#4477aa")).count(), 8);
+    assert!(lines.contains(&"  \"e0\" [fillcolor=\"#4477aa\", pos=\"0,0!\"];"));
+    let bonds: Vec<&&str> = lines.iter().filter(|l| l.contains(" -- ")).collect();
+    assert_eq!(bonds.len(), 32);
+    // the ferromagnetic dimers are dashed, wider and colored apart
+    let dimers: Vec<&&str> = bonds
+        .iter()
+        .filter(|l| l.contains("dimer J = -2"))
+        .copied()
+        .collect();
+    assert_eq!(dimers.len(), 8);
+    for dimer in dimers {
+        assert!(dimer.contains("style=dashed") && dimer.contains("penwidth=4.00"));
+        assert!(dimer.contains("color=\"#ccbb44\""), "{}", dimer);
+    }
+    let bond = bonds.iter().find(|l| l.contains("bond J = 1")).unwrap();
+    assert!(bond.contains("penwidth=2.50") && !bond.contains("style="));
+}
+
+#[test]
+fn svg_is_a_standalone_picture() {
+    let lattice = make_latice(4, 4, 0.5).unwrap();
+    let svg = render::svg(&Graph::from_lattice(&lattice));
+    assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+    assert!(svg.trim_end().ends_with("</svg>"));
+    assert_eq!(svg.matches("<circle").count(), 16);
+    assert_eq!(svg.matches("<title>").count(), 32);
+    // the bonds closing the torus are three units long
+    assert_eq!(svg.matches("stroke-opacity").count(), 8);
+    // one legend entry for the dimers and one for the other bonds
+    assert_eq!(svg.matches("<line").count(), 32 + 2);
+    assert!(svg.contains(">o7</text>"));
+
+    // layers are drawn apart, and labels are escaped
+    let mut graph =
+        Graph::from_lattice(&bilayer(2, 2, [Boundary::Open; 2]).unwrap().build().unwrap());
+    graph.sites[0].label = "<a&b>".to_string();
+    for edge in graph.edges.iter_mut().filter(|e| e.a == "e0") {
+        edge.a = "<a&b>".to_string();
+    }
+    let svg = render::svg(&graph);
+    assert!(svg.contains(">&lt;a&amp;b&gt;</text>"));
+    assert_eq!(svg.matches("<title>").count(), 4 * 2 + 4);
+    let mut centers: Vec<&str> = svg
+        .lines()
+        .filter(|l| l.starts_with("<circle"))
+        .map(|l| l.split(" r=").next().unwrap())
+        .collect();
+    centers.sort();
+    centers.dedup();
+    assert_eq!(centers.len(), 8);
+
+    // graphs without positions are laid out on a circle
+    let mut graph = Graph::from_lattice(&lattice);
+    for site in graph.sites.iter_mut() {
+        site.position = None;
+    }
+    assert_eq!(render::svg(&graph).matches("<circle").count(), 16);
+    assert!(!render::dot(&graph).contains("pos="));
+}
@@ -0,0 +1,66 @@
+mod common;
+
+use common::{assert_agrees, run_chain};
+use montecarlo::ed::ExactDiagonalization;
+use montecarlo::lattice::{Lattice, LatticeConstructor};
+use montecarlo::model::Model;
+
+fn ring(length: usize, coupling: f64) -> Lattice {
+    let mut constructor = LatticeConstructor::new((0..length).collect());
+    for i in 0..length {
+        constructor.add_edge(i, (i + 1) % length, coupling);
+    }
+    constructor.build().unwrap()
+}
+
+#[test]
+fn spin_one_dimer_has_the_total_spin_spectrum() {
+    let mut constructor = LatticeConstructor::new(vec![0, 1]);
+    constructor.add_edge(0, 1, 1.0);
+    let ed = ExactDiagonalization::with_model(
+        &constructor.build().unwrap(),
+        &Model::heisenberg().spin(1.0),
+    );
+    // S_1 . S_2 = (S (S + 1) - 4) / 2 for total spin S = 0, 1, 2
+    assert!((ed.ground_state_energy() + 2.0).abs() < 1e-12);
+    let beta: f64 = 0.8;
+    let levels = [(-2.0, 1.0), (-1.0, 3.0), (1.0, 5.0)];
+    let z: f64 = levels.iter().map(|(e, g)| g * (-beta * e).exp()).sum();
+    let energy = levels
+        .iter()
+        .map(|(e, g)| e * g * (-beta * e).exp())
+        .sum::<f64>()
+        / z;
+    assert!((ed.thermodynamics(beta).energy - energy).abs() < 1e-12);
+}
+
+#[test]
+fn larger_spins_match_exact_diagonalization() {
+    let cases = [
+        (ring(6, 1.0), Model::heisenberg().spin(1.0)),
+        (ring(6, 0.8), Model::xxz(0.5).spin(1.0).field(0.7)),
+        (ring(4, 1.0), Model::heisenberg().spin(1.5)),
+        (
+            ring(4, 1.2),
+            Model::xxz(1.5).spin(1.5).staggered_field(0.3).epsilon(0.1),
+        ),
+    ];
+    for (seed, (lattice, model)) in cases.into_iter().enumerate() {
+        for beta in [1.0, 4.0] {
+            let exact = ExactDiagonalization::with_model(&lattice, &model).thermodynamics(beta);
+            let (chain, _) = run_chain(&lattice, model, beta, seed as u64);
+            let name = format!("{:?} beta={}", model, beta);
+            assert_agrees(&format!("energy {}", name), chain.energy, exact.energy);
+            assert_agrees(
+                &format!("magnetization {}", name),
+                chain.magnetization,
+                exact.magnetization,
+            );
+            assert_agrees(
+                &format!("staggered magnetization {}", name),
+                chain.staggered_magnetization,
+                exact.staggered_magnetization,
+            );
+        }
+    }
+}
@@ -0,0 +1,99 @@
+use montecarlo::ed::ExactDiagonalization;
+use montecarlo::lattice::{make_latice, Lattice};
+use montecarlo::model::Model;
+use montecarlo::rng::chain_rng;
+use montecarlo::simulation::{MonteCarlo, Observable};
+use montecarlo::state::State;
+use montecarlo::tempering::{swap_log_ratio, Tempering};
+
+fn monte_carlo(seed: u64) -> MonteCarlo {
+    MonteCarlo {
+        samples: 20000,
+        nloop: 10,
+        bin_size: 100,
+        bootstraps: 200,
+        seed,
+        ..MonteCarlo::default()
+    }
+}
+
+/// A thermalized configuration with at least one operator on a j1 bond.
+fn configuration(lattice: &Lattice, model: Model, beta: f64, seed: u64) -> State {
+    let rng = &mut chain_rng(seed, 0);
+    let mut state = State::with_model(lattice, model, 10, rng);
+    state.thermalize(beta, rng);
+    state
+}
+
+fn operators_on(state: &State, coupling: f64) -> i64 {
+    state
+        .path
+        .iter()
+        .filter(|(_, op)| op.as_ref().is_some_and(|op| op.edge.coupling == coupling))
+        .count() as i64
+}
+
+#[test]
+fn swap_ratio_is_the_sse_weight_ratio() {
+    let lattice = make_latice(4, 2, 0.5).unwrap();
+    let a = configuration(&lattice, Model::default(), 2.0, 1);
+    let b = configuration(&lattice, Model::default(), 3.0, 2);
+    let expected = (a.n as f64 - b.n as f64) * (3.0f64 / 2.0).ln();
+    assert!((swap_log_ratio(&a, 2.0, &b, 3.0) - expected).abs() < 1e-9);
+
+    // without fields only operators on the j1 bonds feel a change of j1
+    let b = configuration(&make_latice(4, 2, 1.5).unwrap(), Model::default(), 2.0, 3);
+    let expected = (operators_on(&a, 0.5) - operators_on(&b, 1.5)) as f64 * (1.5f64 / 0.5).ln();
+    assert!((swap_log_ratio(&a, 2.0, &b, 2.0) - expected).abs() < 1e-9);
+
+    // with a field the diagonal vertices change as well, but a swap is still
+    // its own reverse
+    let model = Model::default().field(0.4);
+    let a = configuration(&lattice, model, 2.0, 4);
+    let b = configuration(&make_latice(4, 2, 1.5).unwrap(), model, 3.0, 5);
+    let forward = swap_log_ratio(&a, 2.0, &b, 3.0);
+    assert!((forward - swap_log_ratio(&b, 3.0, &a, 2.0)).abs() < 1e-9);
+}
+
+#[test]
+fn ladders_match_exact_diagonalization() {
+    let betas = [1.0, 1.5, 2.0, 3.0];
+    let beta_ladder =
+        Tempering::in_beta(make_latice(4, 2, 0.8).unwrap(), &betas).monte_carlo(monte_carlo(1));
+    let j1s = [0.6, 1.0, 1.4];
+    let lattices = j1s
+        .iter()
+        .map(|&j1| make_latice(4, 2, j1).unwrap())
+        .collect();
+    let j1_ladder = Tempering::in_coupling(lattices, 2.0).monte_carlo(monte_carlo(2));
+
+    for ladder in [beta_ladder, j1_ladder] {
+        let rungs = ladder.rungs().to_vec();
+        let mut result = ladder.run().unwrap();
+        assert_eq!(result.acceptance.len(), rungs.len() - 1);
+        for rate in result.acceptance.iter() {
+            assert!(*rate > 0.0 && *rate <= 1.0, "acceptance {}", rate);
+        }
+        for (rung, measurements) in rungs.iter().zip(result.rungs.iter_mut()) {
+            let exact = ExactDiagonalization::new(&rung.lattice).thermodynamics(rung.beta);
+            for (observable, exact) in [
+                (Observable::Energy, exact.energy),
+                (
+                    Observable::StaggeredMagnetization,
+                    exact.staggered_magnetization,
+                ),
+            ] {
+                let (mean, error) = measurements.estimate(observable);
+                assert!(
+                    (mean - exact).abs() < 4.0 * error + 1e-3,
+                    "{:?} beta={}: sse {} +- {} but exact {}",
+                    observable,
+                    rung.beta,
+                    mean,
+                    error,
+                    exact
+                );
+            }
+        }
+    }
+}
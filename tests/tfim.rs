@@ -0,0 +1,72 @@
+use montecarlo::ed::ExactDiagonalization;
+use montecarlo::lattice::make_latice;
+use montecarlo::rng::chain_rng;
+use montecarlo::simulation::{MonteCarlo, Observable, Simulation};
+use montecarlo::stats;
+use montecarlo::tfim::{IsingState, TransverseIsing};
+
+#[test]
+fn transverse_ising_matches_exact_diagonalization() {
+    let lattice = make_latice(4, 2, 0.7).unwrap();
+    for (seed, field) in [0.3, 1.0, 2.5].into_iter().enumerate() {
+        let model = TransverseIsing::new(field);
+        for beta in [1.0, 4.0] {
+            let exact =
+                ExactDiagonalization::transverse_ising(&lattice, &model).thermodynamics(beta);
+            let rng = &mut chain_rng(seed as u64, beta as u64);
+            let mut s = IsingState::new(&lattice, model, 10, rng);
+            s.thermalize(beta, rng);
+            let mut energies = Vec::new();
+            let mut sms = Vec::new();
+            let mut ms = Vec::new();
+            for _ in 0..20000 {
+                let sample = s.sample(beta, rng);
+                energies.push(sample.energy);
+                sms.push(sample.staggered_magnetization);
+                ms.push(sample.magnetization);
+            }
+            s.verify();
+            for (name, samples, exact) in [
+                ("energy", energies, exact.energy),
+                (
+                    "staggered magnetization",
+                    sms,
+                    exact.staggered_magnetization,
+                ),
+                ("magnetization", ms, exact.magnetization),
+            ] {
+                let (mean, error) = stats::bootstrap(&stats::bin(&samples, 100), 200, rng);
+                assert!(
+                    (mean - exact).abs() < 4.0 * error + 1e-3,
+                    "{} field={} beta={}: sse {} +- {} but exact {}",
+                    name,
+                    field,
+                    beta,
+                    mean,
+                    error,
+                    exact
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn simulation_runs_the_transverse_ising_backend() {
+    let lattice = make_latice(4, 2, 1.0).unwrap();
+    let model = TransverseIsing::new(0.8);
+    let exact = ExactDiagonalization::transverse_ising(&lattice, &model).thermodynamics(2.0);
+    let monte_carlo = MonteCarlo {
+        samples: 20000,
+        chains: 2,
+        ..MonteCarlo::default()
+    };
+    let mut measurements = Simulation::new(lattice, 2.0)
+        .transverse_ising(model)
+        .monte_carlo(monte_carlo)
+        .run()
+        .unwrap();
+    assert_eq!(measurements.energy.chains().count(), 2);
+    let (energy, error) = measurements.estimate(Observable::Energy);
+    assert!((energy - exact.energy).abs() < 4.0 * error + 1e-3);
+}
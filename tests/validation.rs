@@ -0,0 +1,88 @@
+mod common;
+
+use common::{assert_agrees, run_chain};
+use montecarlo::ed::{ground_state, ExactDiagonalization};
+use montecarlo::lattice::{make_latice, LatticeConstructor};
+use montecarlo::model::Model;
+use montecarlo::rng::chain_rng;
+use montecarlo::state::{Sample, State};
+use montecarlo::stats;
+
+#[test]
+fn dimer_spectrum() {
+    let mut constructor = LatticeConstructor::new(vec![0, 1]);
+    constructor.add_edge(0, 1, 2.0);
+    let ed = ExactDiagonalization::new(&constructor.build().unwrap());
+    assert!((ed.ground_state_energy() + 1.5).abs() < 1e-12);
+    // singlet at -3J/4 and triplet at J/4
+    let beta: f64 = 0.7;
+    let z = (1.5 * beta).exp() + 3.0 * (-0.5 * beta).exp();
+    let energy = (-1.5 * (1.5 * beta).exp() + 1.5 * (-0.5 * beta).exp()) / z;
+    assert!((ed.thermodynamics(beta).energy - energy).abs() < 1e-12);
+}
+
+#[test]
+fn lanczos_matches_full_diagonalization() {
+    let lattice = make_latice(4, 2, 0.6).unwrap();
+    let ed = ExactDiagonalization::new(&lattice);
+    let thermo = ed.thermodynamics(200.0);
+    let ground = ground_state(&lattice);
+    assert!((ground.energy - ed.ground_state_energy()).abs() < 1e-9);
+    assert!((ground.energy - thermo.energy).abs() < 1e-9);
+    assert!((ground.staggered_magnetization - thermo.staggered_magnetization).abs() < 1e-6);
+}
+
+#[test]
+fn seeded_chains_are_reproducible() {
+    let lattice = make_latice(4, 2, 0.8).unwrap();
+    let run = |seed| {
+        let rng = &mut chain_rng(seed, 3);
+        let mut s = State::new(&lattice, 10, rng);
+        let samples: Vec<Sample> = (0..200).map(|_| s.sample(10, 2.0, rng)).collect();
+        let (mean, error) = stats::bootstrap(
+            &samples.iter().map(|x| x.energy).collect::<Vec<_>>(),
+            50,
+            rng,
+        );
+        (samples, mean, error)
+    };
+    assert_eq!(run(7), run(7));
+    assert_ne!(run(7).0, run(8).0);
+}
+
+#[test]
+fn small_lattices_match_exact_diagonalization() {
+    let mut seed = 0;
+    for (width, height) in [(2, 2), (4, 2)] {
+        for beta in [0.5, 2.0, 8.0] {
+            for j1 in [0.3, 1.0, 1.7] {
+                let lattice = make_latice(width, height, j1).unwrap();
+                let exact = ExactDiagonalization::new(&lattice).thermodynamics(beta);
+                let (chain, _) = run_chain(&lattice, Model::heisenberg(), beta, seed);
+                let name = format!("{}x{} beta={} j1={}", width, height, beta, j1);
+                assert_agrees(&format!("energy {}", name), chain.energy, exact.energy);
+                assert_agrees(
+                    &format!("staggered magnetization {}", name),
+                    chain.staggered_magnetization,
+                    exact.staggered_magnetization,
+                );
+                seed += 1;
+            }
+        }
+    }
+}
+
+#[test]
+fn four_by_four_matches_ground_state() {
+    for (seed, j1) in [0.5, 1.0].into_iter().enumerate() {
+        let lattice = make_latice(4, 4, j1).unwrap();
+        let exact = ground_state(&lattice);
+        let (chain, _) = run_chain(&lattice, Model::heisenberg(), 16.0, 100 + seed as u64);
+        assert_agrees(&format!("energy j1={}", j1), chain.energy, exact.energy);
+        assert_agrees(
+            &format!("staggered magnetization j1={}", j1),
+            chain.staggered_magnetization,
+            exact.staggered_magnetization,
+        );
+    }
+}